@@ -1,7 +1,11 @@
-use super::id_types::{InstructionSeq, MachineName};
+use super::{
+    id_types::{InstructionSeq, MachineName},
+    protocol::StandardOutput,
+    traceback::ExecError,
+};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt::Display};
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 pub struct ApiMachine {
@@ -13,6 +17,98 @@ pub struct ApiMachine {
 
     #[serde(default)]
     pub tags: HashMap<String, String>,
+
+    /// Memory size in MB. `None` if the server doesn't report it.
+    #[serde(default)]
+    pub memory_mb: Option<u32>,
+
+    /// Number of virtual CPUs. `None` if the server doesn't report it.
+    #[serde(default)]
+    pub vcpus: Option<u32>,
+
+    /// Disk size in MB. `None` if the server doesn't report it.
+    #[serde(default)]
+    pub disk_mb: Option<u32>,
+}
+
+/// A change in a machine's lifecycle, delivered by
+/// [`ForeverVMClient::machine_events`](crate::client::ForeverVMClient::machine_events).
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct MachineEvent {
+    pub kind: MachineEventKind,
+    pub machine_name: MachineName,
+    /// The machine's state just after the event.
+    pub machine: ApiMachine,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MachineEventKind {
+    Created,
+    Started,
+    InstructionReceived,
+    Idle,
+    Suspended,
+    Expired,
+    Deleted,
+    /// An event kind added to the server after this version of the SDK.
+    #[serde(other)]
+    Unknown,
+}
+
+/// A past or pending instruction on a machine, as returned by
+/// [`ForeverVMClient::list_instructions`](crate::client::ForeverVMClient::list_instructions).
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ApiInstruction {
+    pub seq: InstructionSeq,
+    pub code: String,
+    pub status: InstructionStatus,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub runtime_ms: Option<u64>,
+
+    /// The result, once the instruction has finished.
+    #[serde(default)]
+    pub result: Option<ExecResult>,
+
+    /// The instruction's output. Only populated if requested with `include_output`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub output: Vec<StandardOutput>,
+}
+
+impl ApiInstruction {
+    /// Returns true once the instruction has stopped running. An instruction with a result
+    /// has finished even if its status is one this version of the SDK doesn't know.
+    pub fn is_finished(&self) -> bool {
+        self.status.is_finished() || self.result.is_some()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InstructionStatus {
+    Pending,
+    Running,
+    Completed,
+    Error,
+    Interrupted,
+    /// A status added to the server after this version of the SDK.
+    #[serde(other)]
+    Unknown,
+}
+
+impl InstructionStatus {
+    /// Returns true once the instruction has stopped running, whether or not it succeeded.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            InstructionStatus::Completed
+                | InstructionStatus::Error
+                | InstructionStatus::Interrupted
+        )
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -76,13 +172,132 @@ pub enum ExecResultType {
     },
 }
 
+/// How an instruction ended.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecStatus {
+    /// The instruction ran to completion.
+    Ok,
+    /// The code raised an exception.
+    PythonException,
+    /// The instruction ran for longer than its `timeout_seconds`.
+    Timeout,
+    /// The instruction was interrupted before it finished.
+    Interrupted,
+    /// The machine ran out of memory while running the instruction.
+    OutOfMemory,
+    /// The machine failed for a reason unrelated to the code.
+    MachineError,
+    /// A status added to the server after this version of the SDK.
+    #[serde(other)]
+    Unknown,
+}
+
+impl Display for ExecStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            ExecStatus::Ok => "ok",
+            ExecStatus::PythonException => "exception",
+            ExecStatus::Timeout => "timed out",
+            ExecStatus::Interrupted => "interrupted",
+            ExecStatus::OutOfMemory => "out of memory",
+            ExecStatus::MachineError => "machine error",
+            ExecStatus::Unknown => "unknown status",
+        };
+        f.write_str(description)
+    }
+}
+
+/// The outcome of an instruction.
+///
+/// Older servers don't send `status`; for them, it is inferred as
+/// [`ExecStatus::PythonException`] if the payload has an `error` and [`ExecStatus::Ok`]
+/// otherwise. A payload with both an `error` and a `value`, or whose `status` contradicts
+/// which of them it has, is rejected rather than guessed at.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(try_from = "RawExecResult")]
 pub struct ExecResult {
+    pub status: ExecStatus,
+    /// The value or error message. This is always [`ExecResultType::Value`] when `status`
+    /// is [`ExecStatus::Ok`], and [`ExecResultType::Error`] otherwise.
     #[serde(flatten)]
     pub result: ExecResultType,
     pub runtime_ms: u64,
 }
 
+impl ExecResult {
+    pub fn is_ok(&self) -> bool {
+        self.status == ExecStatus::Ok
+    }
+
+    /// The error, if the instruction didn't complete successfully. Use
+    /// [`ExecError::parse`] to get the exception type and stack frames from it.
+    pub fn error(&self) -> Option<ExecError> {
+        match &self.result {
+            ExecResultType::Error { error } => Some(ExecError {
+                status: self.status,
+                message: error.clone(),
+            }),
+            ExecResultType::Value { .. } => None,
+        }
+    }
+}
+
+/// The wire format of [`ExecResult`], before it is checked for consistency.
+#[derive(Deserialize)]
+struct RawExecResult {
+    #[serde(default)]
+    status: Option<ExecStatus>,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    value: Option<String>,
+    #[serde(default)]
+    data: Option<serde_json::Value>,
+    runtime_ms: u64,
+}
+
+impl TryFrom<RawExecResult> for ExecResult {
+    type Error = String;
+
+    fn try_from(raw: RawExecResult) -> Result<Self, Self::Error> {
+        if raw.error.is_some() && (raw.value.is_some() || raw.data.is_some()) {
+            return Err("exec result has both an `error` and a `value` or `data`".to_string());
+        }
+
+        let status = match raw.status {
+            Some(status) => status,
+            None if raw.error.is_some() => ExecStatus::PythonException,
+            None => ExecStatus::Ok,
+        };
+        if status == ExecStatus::Ok && raw.error.is_some() {
+            return Err("exec result has status `ok` but also an `error`".to_string());
+        }
+        if status != ExecStatus::Ok && (raw.value.is_some() || raw.data.is_some()) {
+            return Err(format!(
+                "exec result has status `{status}` but also a `value` or `data`"
+            ));
+        }
+
+        let result = if status == ExecStatus::Ok {
+            ExecResultType::Value {
+                value: raw.value,
+                data: raw.data,
+            }
+        } else {
+            ExecResultType::Error {
+                error: raw.error.unwrap_or_else(|| status.to_string()),
+            }
+        };
+
+        Ok(ExecResult {
+            status,
+            result,
+            runtime_ms: raw.runtime_ms,
+        })
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ApiSignupRequest {
     pub email: String,
@@ -1,20 +1,39 @@
-use super::{api_types::ApiMachine, id_types::MachineName};
+use super::{
+    api_types::{ApiInstruction, ApiMachine},
+    id_types::MachineName,
+    tag_selector::TagSelector,
+};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct WhoamiResponse {
     pub account: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CreateMachineResponse {
     pub machine_name: MachineName,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ListMachinesResponse {
     pub machines: Vec<ApiMachine>,
+
+    /// Pass as `cursor` to fetch the next page. `None` on the last page.
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GetMachineResponse {
+    pub machine: ApiMachine,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GetInstructionResponse {
+    pub instruction: ApiInstruction,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -25,10 +44,145 @@ pub struct CreateMachineRequest {
     /// Memory size in MB. If not specified, a default value will be used.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub memory_mb: Option<u32>,
+
+    /// Delete the machine this many seconds after it is created. At most one of
+    /// `ttl_seconds` and `expires_at` may be set; if neither is, the machine doesn't expire.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl_seconds: Option<u64>,
+
+    /// Delete the machine at this time.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExtendExpiryRequest {
+    /// Seconds to add to the machine's current expiry time.
+    pub extend_by_seconds: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExtendExpiryResponse {
+    pub machine: ApiMachine,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SuspendMachineResponse {
+    pub machine: ApiMachine,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResumeMachineResponse {
+    pub machine: ApiMachine,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResizeMachineRequest {
+    /// New memory size in MB.
+    pub memory_mb: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResizeMachineResponse {
+    pub machine: ApiMachine,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UpdateTagsRequest {
+    /// Tags to add or overwrite.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub set: HashMap<String, String>,
+
+    /// Keys of tags to remove. Keys that aren't set are ignored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub remove: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateTagsResponse {
+    pub machine: ApiMachine,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ListInstructionsRequest {
+    /// Include each instruction's output in the response.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub include_output: bool,
+
+    /// Order by sequence number. Defaults to ascending (oldest first).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_order: Option<SortOrder>,
+
+    /// Maximum number of instructions per page. If not specified, the server's default is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+
+    /// The `next_cursor` of the previous page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListInstructionsResponse {
+    pub instructions: Vec<ApiInstruction>,
+
+    /// Pass as `cursor` to fetch the next page. `None` on the last page.
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MachineEventsRequest {
+    /// Only deliver events for machines with all of these tags set to exactly these values.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tags: HashMap<String, String>,
+
+    /// Only deliver events for machines whose tags satisfy all of these selectors.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tag_selectors: Vec<TagSelector>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct ListMachinesRequest {
+    /// Only return machines with all of these tags set to exactly these values.
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub tags: HashMap<String, String>,
+
+    /// Only return machines whose tags satisfy all of these selectors.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tag_selectors: Vec<TagSelector>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub running: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_pending_instruction: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_by: Option<MachineSortKey>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_order: Option<SortOrder>,
+
+    /// Maximum number of machines per page. If not specified, the server's default is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+
+    /// The `next_cursor` of the previous page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MachineSortKey {
+    CreatedAt,
+    ExpiresAt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    Desc,
 }
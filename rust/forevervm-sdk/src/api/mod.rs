@@ -5,7 +5,9 @@ pub mod api_types;
 pub mod http_api;
 pub mod id_types;
 pub mod protocol;
+pub mod tag_selector;
 pub mod token;
+pub mod traceback;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ApiErrorResponse {
@@ -0,0 +1,153 @@
+//! Label-selector style filters on machine tags.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+/// A condition on a machine's tags, written like a Kubernetes label selector:
+///
+/// | Syntax              | Matches machines where…              |
+/// |---------------------|--------------------------------------|
+/// | `key=value`         | the tag `key` is `value`             |
+/// | `key!=value`        | the tag `key` is missing or not `value` |
+/// | `key in (a,b)`      | the tag `key` is `a` or `b`          |
+/// | `key notin (a,b)`   | the tag `key` is missing or neither `a` nor `b` |
+/// | `key exists`, `key` | the tag `key` is set                 |
+/// | `!key`              | the tag `key` is not set             |
+///
+/// Selectors are sent to the server in this string form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagSelector {
+    Equals(String, String),
+    NotEquals(String, String),
+    In(String, Vec<String>),
+    NotIn(String, Vec<String>),
+    Exists(String),
+    NotExists(String),
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum TagSelectorError {
+    #[error("Empty tag selector")]
+    Empty,
+
+    #[error("Invalid tag key `{0}`")]
+    InvalidKey(String),
+
+    #[error("Invalid value list `{0}`; expected `(a,b,...)`")]
+    InvalidValueList(String),
+}
+
+impl TagSelector {
+    /// Returns true if a machine with the given tags satisfies this selector.
+    pub fn matches(&self, tags: &HashMap<String, String>) -> bool {
+        match self {
+            TagSelector::Equals(key, value) => tags.get(key) == Some(value),
+            TagSelector::NotEquals(key, value) => tags.get(key) != Some(value),
+            TagSelector::In(key, values) => tags.get(key).is_some_and(|v| values.contains(v)),
+            TagSelector::NotIn(key, values) => !tags.get(key).is_some_and(|v| values.contains(v)),
+            TagSelector::Exists(key) => tags.contains_key(key),
+            TagSelector::NotExists(key) => !tags.contains_key(key),
+        }
+    }
+}
+
+fn parse_key(key: &str) -> Result<String, TagSelectorError> {
+    let key = key.trim();
+    let valid = !key.is_empty()
+        && !key
+            .chars()
+            .any(|c| c.is_whitespace() || "=!(),".contains(c));
+
+    if valid {
+        Ok(key.to_string())
+    } else {
+        Err(TagSelectorError::InvalidKey(key.to_string()))
+    }
+}
+
+fn parse_value_list(list: &str) -> Result<Vec<String>, TagSelectorError> {
+    let inner = list
+        .trim()
+        .strip_prefix('(')
+        .and_then(|rest| rest.strip_suffix(')'))
+        .ok_or_else(|| TagSelectorError::InvalidValueList(list.trim().to_string()))?;
+
+    Ok(inner
+        .split(',')
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect())
+}
+
+impl FromStr for TagSelector {
+    type Err = TagSelectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(TagSelectorError::Empty);
+        }
+
+        if let Some(key) = s.strip_prefix('!') {
+            return Ok(TagSelector::NotExists(parse_key(key)?));
+        }
+        if let Some(key) = s.strip_suffix(" exists") {
+            return Ok(TagSelector::Exists(parse_key(key)?));
+        }
+        if let Some((key, values)) = s.split_once(" notin ") {
+            return Ok(TagSelector::NotIn(
+                parse_key(key)?,
+                parse_value_list(values)?,
+            ));
+        }
+        if let Some((key, values)) = s.split_once(" in ") {
+            return Ok(TagSelector::In(parse_key(key)?, parse_value_list(values)?));
+        }
+        if let Some((key, value)) = s.split_once("!=") {
+            return Ok(TagSelector::NotEquals(
+                parse_key(key)?,
+                value.trim().to_string(),
+            ));
+        }
+        if let Some((key, value)) = s.split_once("==").or_else(|| s.split_once('=')) {
+            return Ok(TagSelector::Equals(
+                parse_key(key)?,
+                value.trim().to_string(),
+            ));
+        }
+
+        Ok(TagSelector::Exists(parse_key(s)?))
+    }
+}
+
+impl Display for TagSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TagSelector::Equals(key, value) => write!(f, "{key}={value}"),
+            TagSelector::NotEquals(key, value) => write!(f, "{key}!={value}"),
+            TagSelector::In(key, values) => write!(f, "{key} in ({})", values.join(",")),
+            TagSelector::NotIn(key, values) => write!(f, "{key} notin ({})", values.join(",")),
+            TagSelector::Exists(key) => write!(f, "{key} exists"),
+            TagSelector::NotExists(key) => write!(f, "!{key}"),
+        }
+    }
+}
+
+impl Serialize for TagSelector {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for TagSelector {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
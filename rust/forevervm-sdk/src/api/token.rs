@@ -1,24 +1,43 @@
+use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
-use std::{fmt::Display, str::FromStr};
+use std::{
+    fmt::{Debug, Display},
+    str::FromStr,
+};
 
 const SEPARATOR: &str = ".";
+const REDACTED: &str = "[REDACTED]";
 
-#[derive(Debug, Clone)]
+/// An API token of the form `<id>.<secret>`.
+///
+/// The secret part is zeroized on drop and is never included in `Debug` or `Display`
+/// output; use [`ApiToken::expose_secret`] to obtain the full token when it is actually
+/// needed, e.g. to build an `Authorization` header. Serializing the token writes the full
+/// value, so that it can be stored in a credentials file.
+#[derive(Clone)]
 pub struct ApiToken {
     pub id: String,
-    pub token: String,
+    token: SecretString,
 }
 
 impl ApiToken {
     pub fn new(token: String) -> Result<Self, ApiTokenError> {
-        let (id, token) = token
+        let token = SecretString::from(token);
+        let (id, _) = token
+            .expose_secret()
             .split_once(SEPARATOR)
             .ok_or(ApiTokenError::InvalidFormat)?;
+
         Ok(Self {
             id: id.to_string(),
-            token: token.to_string(),
+            token,
         })
     }
+
+    /// Returns the full token, including the secret part.
+    pub fn expose_secret(&self) -> &str {
+        self.token.expose_secret()
+    }
 }
 
 impl Serialize for ApiToken {
@@ -26,7 +45,7 @@ impl Serialize for ApiToken {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&self.to_string())
+        serializer.serialize_str(self.expose_secret())
     }
 }
 
@@ -36,7 +55,7 @@ impl<'de> Deserialize<'de> for ApiToken {
         D: serde::Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        Self::from_str(&s).map_err(serde::de::Error::custom)
+        Self::new(s).map_err(serde::de::Error::custom)
     }
 }
 
@@ -50,18 +69,22 @@ impl FromStr for ApiToken {
     type Err = ApiTokenError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (id, token) = s
-            .split_once(SEPARATOR)
-            .ok_or(ApiTokenError::InvalidFormat)?;
-        Ok(Self {
-            id: id.to_string(),
-            token: token.to_string(),
-        })
+        Self::new(s.to_string())
     }
 }
 
+/// Displays the token ID with the secret redacted.
 impl Display for ApiToken {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}{}{}", self.id, SEPARATOR, self.token)
+        write!(f, "{}{}{}", self.id, SEPARATOR, REDACTED)
+    }
+}
+
+impl Debug for ApiToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiToken")
+            .field("id", &self.id)
+            .field("token", &REDACTED)
+            .finish()
     }
 }
@@ -0,0 +1,206 @@
+//! Parsing of the Python tracebacks returned as instruction errors.
+
+use super::api_types::ExecStatus;
+
+/// The error of an instruction that didn't complete successfully, as returned by
+/// [`ExecResult::error`](super::api_types::ExecResult::error).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecError {
+    pub status: ExecStatus,
+    /// The error text, usually a Python traceback.
+    pub message: String,
+}
+
+/// A Python exception, parsed from the traceback CPython prints for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PythonException {
+    /// The exception's class name, e.g. `ValueError` or `json.decoder.JSONDecodeError`.
+    pub exc_type: String,
+    /// Everything after the class name, which may span several lines. Empty if the exception
+    /// has no message.
+    pub message: String,
+    /// Stack frames, outermost first. For a `SyntaxError`, the last frame is the location
+    /// of the error in the code being compiled.
+    pub frames: Vec<Frame>,
+    /// The exceptions that led to this one, starting with the one it was raised from or
+    /// while handling, and ending with the original exception. Their own `cause_chain`s
+    /// are empty.
+    pub cause_chain: Vec<ChainedException>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub file: String,
+    pub line: u32,
+    /// The function name. `None` for the location of a `SyntaxError`, which isn't in
+    /// a function.
+    pub function: Option<String>,
+    /// The line of code, with leading whitespace removed, if CPython could show it.
+    pub source: Option<String>,
+    /// The 1-based column within `source` that CPython marked with a caret, if any.
+    pub column: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainedException {
+    pub exception: PythonException,
+    /// How the exception after this one in the chain came about.
+    pub relation: ChainRelation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainRelation {
+    /// The next exception was raised with `raise ... from` this one.
+    Cause,
+    /// The next exception was raised while this one was being handled.
+    Context,
+}
+
+const CAUSE_SEPARATOR: &str =
+    "The above exception was the direct cause of the following exception:";
+const CONTEXT_SEPARATOR: &str =
+    "During handling of the above exception, another exception occurred:";
+
+/// Source lines in a traceback are indented by four spaces.
+const SOURCE_INDENT: usize = 4;
+
+impl ExecError {
+    /// Parses the error text as a CPython traceback. Returns `None` if it isn't one, e.g.
+    /// for errors reported by the machine rather than by Python.
+    pub fn parse(&self) -> Option<PythonException> {
+        parse_traceback(&self.message)
+    }
+}
+
+/// Parses a traceback, possibly with chained exceptions, as printed by CPython.
+pub fn parse_traceback(text: &str) -> Option<PythonException> {
+    let mut sections: Vec<(Vec<&str>, Option<ChainRelation>)> = vec![(Vec::new(), None)];
+    for line in text.lines() {
+        let relation = match line.trim() {
+            CAUSE_SEPARATOR => Some(ChainRelation::Cause),
+            CONTEXT_SEPARATOR => Some(ChainRelation::Context),
+            _ => None,
+        };
+
+        match relation {
+            Some(relation) => {
+                if let Some(last) = sections.last_mut() {
+                    last.1 = Some(relation);
+                }
+                sections.push((Vec::new(), None));
+            }
+            None => {
+                if let Some(last) = sections.last_mut() {
+                    last.0.push(line);
+                }
+            }
+        }
+    }
+
+    let mut exceptions = Vec::with_capacity(sections.len());
+    for (lines, relation) in sections {
+        exceptions.push((parse_section(&lines)?, relation));
+    }
+
+    let (mut exception, _) = exceptions.pop()?;
+    exception.cause_chain = exceptions
+        .into_iter()
+        .rev()
+        .map(|(exception, relation)| ChainedException {
+            exception,
+            relation: relation.unwrap_or(ChainRelation::Context),
+        })
+        .collect();
+
+    Some(exception)
+}
+
+/// Parses a single exception's traceback: an optional header, its frames, and the
+/// exception line.
+fn parse_section(lines: &[&str]) -> Option<PythonException> {
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut lines = lines
+        .iter()
+        .copied()
+        .skip_while(|line| line.trim().is_empty())
+        .peekable();
+
+    if lines
+        .peek()
+        .is_some_and(|line| line.starts_with("Traceback (most recent call last):"))
+    {
+        lines.next();
+    }
+
+    while let Some(line) = lines.next_if(|line| line.starts_with(' ')) {
+        let trimmed = line.trim();
+        if let Some(frame) = parse_frame_line(trimmed) {
+            frames.push(frame);
+        } else if let Some(frame) = frames.last_mut() {
+            if is_caret_line(trimmed) {
+                frame.column = line
+                    .find('^')
+                    .and_then(|index| index.checked_sub(SOURCE_INDENT - 1));
+            } else if frame.source.is_none() && !trimmed.starts_with("[Previous line repeated") {
+                frame.source = Some(trimmed.to_string());
+            }
+        }
+    }
+
+    let exception_line = lines.next()?;
+    let (exc_type, first_line) = match exception_line.split_once(':') {
+        Some((exc_type, rest)) => (exc_type, rest.trim_start()),
+        None => (exception_line.trim_end(), ""),
+    };
+    if !is_exception_type(exc_type) {
+        return None;
+    }
+
+    let mut message = first_line.to_string();
+    for line in lines {
+        message.push('\n');
+        message.push_str(line);
+    }
+
+    Some(PythonException {
+        exc_type: exc_type.to_string(),
+        message: message.trim_end().to_string(),
+        frames,
+        cause_chain: Vec::new(),
+    })
+}
+
+/// Parses `File "<file>", line <n>, in <function>`, where the function is absent for the
+/// location of a `SyntaxError`.
+fn parse_frame_line(line: &str) -> Option<Frame> {
+    let rest = line.strip_prefix("File \"")?;
+    let (file, rest) = rest.split_once("\", line ")?;
+    let (line_number, function) = match rest.split_once(", in ") {
+        Some((line_number, function)) => (line_number, Some(function.to_string())),
+        None => (rest, None),
+    };
+
+    Some(Frame {
+        file: file.to_string(),
+        line: line_number.trim().parse().ok()?,
+        function,
+        source: None,
+        column: None,
+    })
+}
+
+/// Caret lines mark the failing part of the line above, e.g. `^^^^` or `~~~^~~`.
+fn is_caret_line(line: &str) -> bool {
+    line.contains('^') && line.chars().all(|c| matches!(c, '^' | '~' | ' '))
+}
+
+/// A dotted Python identifier, such as `KeyError` or `module.CustomError`.
+fn is_exception_type(s: &str) -> bool {
+    !s.is_empty()
+        && s.split('.').all(|part| {
+            part.chars()
+                .next()
+                .is_some_and(|c| c.is_alphabetic() || c == '_')
+                && part.chars().all(|c| c.is_alphanumeric() || c == '_')
+        })
+}
@@ -0,0 +1,425 @@
+//! A synchronous facade over the async client, for programs that don't run a tokio runtime.
+//!
+//! Each [`ForeverVMClient`] owns a small runtime and drives the async client on it. Values
+//! derived from a client (REPL connections, output streams) share the same runtime, so they
+//! stay usable after the client itself is dropped.
+//!
+//! These types block the calling thread, so they must not be used from within an async
+//! context; doing so panics.
+//!
+//! ```no_run
+//! # fn example(api_base: url::Url, token: forevervm_sdk::api::token::ApiToken) -> forevervm_sdk::client::error::Result<()> {
+//! use forevervm_sdk::{api::http_api::CreateMachineRequest, blocking::ForeverVMClient};
+//!
+//! let client = ForeverVMClient::new(api_base, token)?;
+//! let machine = client.create_machine(CreateMachineRequest::default())?;
+//!
+//! let mut repl = client.repl(&machine.machine_name)?;
+//! let mut handle = repl.exec("print('hello'); 1 + 1")?;
+//! for chunk in &mut handle {
+//!     println!("{}", chunk?.data);
+//! }
+//! println!("{:?}", handle.result()?);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    api::{
+        api_types::{
+            ApiExecResponse, ApiExecResultResponse, ApiInstruction, ApiMachine, ExecResult,
+            Instruction, MachineEvent,
+        },
+        http_api::{
+            CreateMachineRequest, CreateMachineResponse, ListInstructionsRequest,
+            ListInstructionsResponse, ListMachinesRequest, ListMachinesResponse,
+            MachineEventsRequest, WhoamiResponse,
+        },
+        id_types::{InstructionSeq, MachineName},
+        protocol::{MessageFromServer, StandardOutput},
+        token::ApiToken,
+    },
+    client::{
+        self,
+        error::Result,
+        exec::{BatchOptions, ExecOptions, ExecOutput},
+        job::JobHandle,
+    },
+};
+use futures_util::{Stream, StreamExt};
+use reqwest::Url;
+use std::{collections::HashMap, pin::Pin, sync::Arc, time::Duration};
+use tokio::runtime::Runtime;
+
+/// Blocking counterpart of [`client::ForeverVMClient`].
+#[derive(Debug)]
+pub struct ForeverVMClient {
+    inner: client::ForeverVMClient,
+    runtime: Arc<Runtime>,
+}
+
+impl ForeverVMClient {
+    pub fn new(api_base: Url, token: ApiToken) -> Result<Self> {
+        Self::from_async(client::ForeverVMClient::new(api_base, token))
+    }
+
+    /// Wraps an async client, e.g. one configured with
+    /// [`ForeverVMClient::builder`](client::ForeverVMClient::builder).
+    pub fn from_async(inner: client::ForeverVMClient) -> Result<Self> {
+        Ok(Self {
+            inner,
+            runtime: new_runtime()?,
+        })
+    }
+
+    pub fn server_url(&self) -> &Url {
+        self.inner.server_url()
+    }
+
+    pub fn repl(&self, machine_name: &MachineName) -> Result<ReplConnection> {
+        let inner = self.runtime.block_on(self.inner.repl(machine_name))?;
+
+        Ok(ReplConnection {
+            machine_name: inner.machine_name.clone(),
+            inner,
+            runtime: self.runtime.clone(),
+        })
+    }
+
+    pub fn create_machine(&self, options: CreateMachineRequest) -> Result<CreateMachineResponse> {
+        self.runtime.block_on(self.inner.create_machine(options))
+    }
+
+    pub fn list_machines(&self, options: ListMachinesRequest) -> Result<ListMachinesResponse> {
+        self.runtime.block_on(self.inner.list_machines(options))
+    }
+
+    pub fn get_machine(&self, machine_name: &MachineName) -> Result<ApiMachine> {
+        self.runtime.block_on(self.inner.get_machine(machine_name))
+    }
+
+    pub fn exec(
+        &self,
+        instruction: Instruction,
+        machine_name: Option<&MachineName>,
+    ) -> Result<ApiExecResponse> {
+        self.runtime
+            .block_on(self.inner.exec(instruction, machine_name))
+    }
+
+    pub fn exec_with_options(
+        &self,
+        instruction: Instruction,
+        machine_name: Option<&MachineName>,
+        options: &ExecOptions,
+    ) -> Result<ApiExecResponse> {
+        self.runtime.block_on(
+            self.inner
+                .exec_with_options(instruction, machine_name, options),
+        )
+    }
+
+    pub fn interrupt_machine(&self, machine_name: &MachineName) -> Result<ApiExecResponse> {
+        self.runtime
+            .block_on(self.inner.interrupt_machine(machine_name))
+    }
+
+    pub fn suspend_machine(&self, machine_name: &MachineName) -> Result<ApiMachine> {
+        self.runtime
+            .block_on(self.inner.suspend_machine(machine_name))
+    }
+
+    pub fn resume_machine(
+        &self,
+        machine_name: &MachineName,
+        timeout: Duration,
+    ) -> Result<ApiMachine> {
+        self.runtime
+            .block_on(self.inner.resume_machine(machine_name, timeout))
+    }
+
+    pub fn wait_idle(&self, machine_name: &MachineName, timeout: Duration) -> Result<ApiMachine> {
+        self.runtime
+            .block_on(self.inner.wait_idle(machine_name, timeout))
+    }
+
+    pub fn wait_running(
+        &self,
+        machine_name: &MachineName,
+        timeout: Duration,
+    ) -> Result<ApiMachine> {
+        self.runtime
+            .block_on(self.inner.wait_running(machine_name, timeout))
+    }
+
+    pub fn resize_machine(&self, machine_name: &MachineName, memory_mb: u32) -> Result<ApiMachine> {
+        self.runtime
+            .block_on(self.inner.resize_machine(machine_name, memory_mb))
+    }
+
+    pub fn update_tags(
+        &self,
+        machine_name: &MachineName,
+        set: HashMap<String, String>,
+        remove: Vec<String>,
+    ) -> Result<ApiMachine> {
+        self.runtime
+            .block_on(self.inner.update_tags(machine_name, set, remove))
+    }
+
+    pub fn extend_expiry(&self, machine_name: &MachineName, by: Duration) -> Result<ApiMachine> {
+        self.runtime
+            .block_on(self.inner.extend_expiry(machine_name, by))
+    }
+
+    pub fn delete_machine(&self, machine_name: &MachineName) -> Result<()> {
+        self.runtime
+            .block_on(self.inner.delete_machine(machine_name))
+    }
+
+    /// Blocking counterpart of [`client::ForeverVMClient::machines`].
+    pub fn machines(&self, query: ListMachinesRequest) -> MachineIter<'_> {
+        MachineIter {
+            stream: self.inner.machines(query),
+            runtime: &self.runtime,
+        }
+    }
+
+    pub fn list_instructions(
+        &self,
+        machine_name: &MachineName,
+        options: ListInstructionsRequest,
+    ) -> Result<ListInstructionsResponse> {
+        self.runtime
+            .block_on(self.inner.list_instructions(machine_name, options))
+    }
+
+    pub fn get_instruction(
+        &self,
+        machine_name: &MachineName,
+        instruction: InstructionSeq,
+    ) -> Result<ApiInstruction> {
+        self.runtime
+            .block_on(self.inner.get_instruction(machine_name, instruction))
+    }
+
+    pub fn wait_instruction(
+        &self,
+        machine_name: &MachineName,
+        instruction: InstructionSeq,
+        timeout: Duration,
+    ) -> Result<ApiInstruction> {
+        self.runtime.block_on(
+            self.inner
+                .wait_instruction(machine_name, instruction, timeout),
+        )
+    }
+
+    /// Blocking counterpart of [`client::ForeverVMClient::submit_job`]. The job can be
+    /// checked on with [`ForeverVMClient::get_instruction`],
+    /// [`ForeverVMClient::wait_instruction`] and [`ForeverVMClient::exec_result_stream`].
+    pub fn submit_job(
+        &self,
+        instruction: Instruction,
+        machine_name: Option<&MachineName>,
+    ) -> Result<JobHandle> {
+        self.runtime
+            .block_on(self.inner.submit_job(instruction, machine_name))
+    }
+
+    pub fn exec_instruction(
+        &self,
+        machine_name: &MachineName,
+        instruction: Instruction,
+    ) -> Result<ApiExecResponse> {
+        self.runtime
+            .block_on(self.inner.exec_instruction(machine_name, instruction))
+    }
+
+    pub fn exec_and_wait(
+        &self,
+        machine_name: &MachineName,
+        instruction: Instruction,
+    ) -> Result<ExecOutput> {
+        self.runtime
+            .block_on(self.inner.exec_and_wait(machine_name, instruction))
+    }
+
+    pub fn exec_and_wait_with(
+        &self,
+        machine_name: &MachineName,
+        instruction: Instruction,
+        on_output: impl FnMut(&StandardOutput),
+    ) -> Result<ExecOutput> {
+        self.runtime.block_on(
+            self.inner
+                .exec_and_wait_with(machine_name, instruction, on_output),
+        )
+    }
+
+    pub fn exec_and_wait_with_options(
+        &self,
+        machine_name: &MachineName,
+        instruction: Instruction,
+        options: &ExecOptions,
+        on_output: impl FnMut(&StandardOutput),
+    ) -> Result<ExecOutput> {
+        self.runtime.block_on(self.inner.exec_and_wait_with_options(
+            machine_name,
+            instruction,
+            options,
+            on_output,
+        ))
+    }
+
+    pub fn exec_batch(
+        &self,
+        machine_name: &MachineName,
+        instructions: Vec<Instruction>,
+        options: BatchOptions,
+    ) -> Result<Vec<ExecOutput>> {
+        self.runtime
+            .block_on(self.inner.exec_batch(machine_name, instructions, options))
+    }
+
+    pub fn exec_result(
+        &self,
+        machine_name: &MachineName,
+        instruction: InstructionSeq,
+    ) -> Result<ApiExecResultResponse> {
+        self.runtime
+            .block_on(self.inner.exec_result(machine_name, instruction))
+    }
+
+    pub fn whoami(&self) -> Result<WhoamiResponse> {
+        self.runtime.block_on(self.inner.whoami())
+    }
+
+    /// Blocking counterpart of
+    /// [`client::ForeverVMClient::exec_result_stream`]; each call to `next` waits for
+    /// the next message from the server.
+    pub fn exec_result_stream(
+        &self,
+        machine_name: &MachineName,
+        instruction: InstructionSeq,
+    ) -> Result<MessageIter> {
+        let stream = self
+            .runtime
+            .block_on(self.inner.exec_result_stream(machine_name, instruction))?;
+
+        Ok(StreamIter {
+            stream,
+            runtime: self.runtime.clone(),
+        })
+    }
+
+    /// Blocking counterpart of [`client::ForeverVMClient::machine_events`].
+    pub fn machine_events(&self, filter: MachineEventsRequest) -> Result<StreamIter<MachineEvent>> {
+        let stream = self.runtime.block_on(self.inner.machine_events(filter))?;
+
+        Ok(StreamIter {
+            stream,
+            runtime: self.runtime.clone(),
+        })
+    }
+}
+
+/// Iterator over a stream of values from the server, such as a streamed instruction result
+/// or machine events.
+pub struct StreamIter<T> {
+    stream: Pin<Box<dyn Stream<Item = Result<T>> + Send>>,
+    runtime: Arc<Runtime>,
+}
+
+/// Iterator over the messages of a streamed instruction result.
+pub type MessageIter = StreamIter<MessageFromServer>;
+
+impl<T> Iterator for StreamIter<T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.stream.next())
+    }
+}
+
+/// Iterator over machines, fetching further pages as needed.
+pub struct MachineIter<'a> {
+    stream: Pin<Box<dyn Stream<Item = Result<ApiMachine>> + Send + 'a>>,
+    runtime: &'a Runtime,
+}
+
+impl Iterator for MachineIter<'_> {
+    type Item = Result<ApiMachine>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.stream.next())
+    }
+}
+
+/// Blocking counterpart of [`client::repl::ReplConnection`].
+pub struct ReplConnection {
+    pub machine_name: MachineName,
+    inner: client::repl::ReplConnection,
+    runtime: Arc<Runtime>,
+}
+
+impl ReplConnection {
+    pub fn exec(&mut self, code: &str) -> Result<ExecResultHandle> {
+        let inner = self.runtime.block_on(self.inner.exec(code))?;
+        Ok(ExecResultHandle {
+            inner,
+            runtime: self.runtime.clone(),
+        })
+    }
+
+    pub fn exec_instruction(&mut self, instruction: Instruction) -> Result<ExecResultHandle> {
+        self.exec_instruction_with_options(instruction, &ExecOptions::default())
+    }
+
+    pub fn exec_instruction_with_options(
+        &mut self,
+        instruction: Instruction,
+        options: &ExecOptions,
+    ) -> Result<ExecResultHandle> {
+        let inner = self.runtime.block_on(
+            self.inner
+                .exec_instruction_with_options(instruction, options),
+        )?;
+        Ok(ExecResultHandle {
+            inner,
+            runtime: self.runtime.clone(),
+        })
+    }
+}
+
+/// Blocking counterpart of [`client::repl::ExecResultHandle`]. Iterating yields the
+/// instruction's output until it completes, or an error if its [`ExecOptions`] stopped it;
+/// [`ExecResultHandle::result`] then returns its result.
+pub struct ExecResultHandle {
+    inner: client::repl::ExecResultHandle,
+    runtime: Arc<Runtime>,
+}
+
+impl ExecResultHandle {
+    pub fn result(self) -> Result<ExecResult> {
+        self.runtime.block_on(self.inner.result())
+    }
+}
+
+impl Iterator for ExecResultHandle {
+    type Item = Result<StandardOutput>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.inner.next())
+    }
+}
+
+/// The REPL's receive loop runs as a spawned task, so it needs a worker thread of its own
+/// to make progress while the caller isn't blocked on the runtime.
+fn new_runtime() -> Result<Arc<Runtime>> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()?;
+    Ok(Arc::new(runtime))
+}
@@ -0,0 +1,143 @@
+use super::{
+    error::Result,
+    middleware::Middleware,
+    proxy::{ProxyConfig, ProxySetting},
+    tls::TlsConfig,
+    transport::{HttpTransport, ReqwestTransport, SocketTransport, TungsteniteTransport},
+    ForeverVMClient,
+};
+use crate::api::token::ApiToken;
+use reqwest::{Client, Url};
+use std::sync::Arc;
+
+/// Builder for a [`ForeverVMClient`] with non-default connection settings.
+///
+/// ```no_run
+/// # use forevervm_sdk::client::{ForeverVMClient, proxy::ProxyConfig};
+/// # fn example(api_base: url::Url, token: forevervm_sdk::api::token::ApiToken) -> forevervm_sdk::client::error::Result<()> {
+/// let proxy = ProxyConfig::new("http://proxy.internal:3128".parse()?)?
+///     .basic_auth("user", "password")
+///     .no_proxy("localhost,.internal");
+/// let client = ForeverVMClient::builder(api_base, token).proxy(proxy).build()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ForeverVMClientBuilder {
+    api_base: Url,
+    token: ApiToken,
+    proxy: ProxySetting,
+    tls: Option<TlsConfig>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    http_transport: Option<Arc<dyn HttpTransport>>,
+    socket_transport: Option<Arc<dyn SocketTransport>>,
+    #[cfg(feature = "validate-syntax")]
+    validate_syntax: bool,
+}
+
+impl ForeverVMClientBuilder {
+    pub(crate) fn new(api_base: Url, token: ApiToken) -> Self {
+        Self {
+            api_base,
+            token,
+            proxy: ProxySetting::default(),
+            tls: None,
+            middleware: Vec::new(),
+            http_transport: None,
+            socket_transport: None,
+            #[cfg(feature = "validate-syntax")]
+            validate_syntax: false,
+        }
+    }
+
+    /// Sends all requests, including REPL WebSocket connections, through the given proxy.
+    pub fn proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.proxy = ProxySetting::Explicit(proxy);
+        self
+    }
+
+    /// Disables proxy use, including detection from environment variables.
+    pub fn no_proxy(mut self) -> Self {
+        self.proxy = ProxySetting::Disabled;
+        self
+    }
+
+    /// Detects the proxy from `HTTPS_PROXY`, `HTTP_PROXY`, `ALL_PROXY` and `NO_PROXY`.
+    /// This is the default.
+    pub fn proxy_from_env(mut self) -> Self {
+        self.proxy = ProxySetting::FromEnv;
+        self
+    }
+
+    /// Uses a custom TLS configuration (trusted roots, client certificate, pinning) for
+    /// both HTTP requests and REPL WebSocket connections.
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Adds a middleware. Middleware sees requests in the order it was added.
+    pub fn middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Sends HTTP requests with a custom transport instead of `reqwest`. Proxy and TLS
+    /// settings are not applied to a custom transport.
+    pub fn http_transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.http_transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Opens REPL connections with a custom transport instead of `tokio-tungstenite`.
+    /// Proxy and TLS settings are not applied to a custom transport.
+    pub fn socket_transport(mut self, transport: impl SocketTransport + 'static) -> Self {
+        self.socket_transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Checks code for Python syntax errors locally before sending it, in
+    /// [`ForeverVMClient::exec_instruction`] and in REPL connections opened by the client.
+    /// Invalid code fails with [`ClientError::SyntaxError`](super::error::ClientError::SyntaxError)
+    /// without reaching the machine.
+    #[cfg(feature = "validate-syntax")]
+    pub fn validate_syntax(mut self, enabled: bool) -> Self {
+        self.validate_syntax = enabled;
+        self
+    }
+
+    pub fn build(self) -> Result<ForeverVMClient> {
+        let tls = self.tls.as_ref().map(TlsConfig::build).transpose()?;
+
+        let http = match self.http_transport {
+            Some(transport) => transport,
+            None => {
+                let mut builder = Client::builder();
+                match &self.proxy {
+                    // reqwest reads the proxy environment variables itself.
+                    ProxySetting::FromEnv => {}
+                    ProxySetting::Disabled => builder = builder.no_proxy(),
+                    ProxySetting::Explicit(proxy) => builder = builder.proxy(proxy.to_reqwest()?),
+                }
+                if let Some(tls) = &tls {
+                    builder = builder.use_preconfigured_tls(rustls::ClientConfig::clone(tls));
+                }
+
+                Arc::new(ReqwestTransport::new(builder.build()?))
+            }
+        };
+
+        let socket = self
+            .socket_transport
+            .unwrap_or_else(|| Arc::new(TungsteniteTransport::new(self.proxy, tls)));
+
+        Ok(ForeverVMClient {
+            api_base: self.api_base,
+            token: self.token,
+            http,
+            socket,
+            middleware: self.middleware,
+            #[cfg(feature = "validate-syntax")]
+            validate_syntax: self.validate_syntax,
+        })
+    }
+}
@@ -1,3 +1,4 @@
+use super::{exec::BatchError, syntax::SyntaxError};
 use crate::api::ApiErrorResponse;
 
 pub type Result<T> = std::result::Result<T, ClientError>;
@@ -28,9 +29,44 @@ pub enum ClientError {
     #[error("Http")]
     HttpError(#[from] tungstenite::http::Error),
 
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    #[error("Proxy error: {0}")]
+    ProxyError(String),
+
+    #[error("TLS configuration error: {0}")]
+    TlsConfigError(String),
+
     #[error("Instruction interrupted")]
     InstructionInterrupted,
 
+    #[error("Server did not assign a sequence number to the instruction")]
+    MissingInstructionSeq,
+
+    #[error("Timed out after {0:?}")]
+    Timeout(std::time::Duration),
+
+    #[error("Cancelled")]
+    Cancelled,
+
+    #[error("Deadline exceeded")]
+    DeadlineExceeded,
+
+    #[error("Batch failed: {0}")]
+    Batch(Box<BatchError>),
+
+    #[error("Syntax error: {0}")]
+    SyntaxError(#[from] SyntaxError),
+
     #[error("Other error: {0}")]
     Other(String),
 }
+
+impl ClientError {
+    /// Returns true if this error came from an
+    /// [`ExecOptions`](super::exec::ExecOptions) deadline or cancellation.
+    pub(crate) fn is_stopped(&self) -> bool {
+        matches!(self, ClientError::Cancelled | ClientError::DeadlineExceeded)
+    }
+}
@@ -0,0 +1,158 @@
+use super::error::{ClientError, Result};
+use crate::api::{
+    api_types::ExecResult,
+    id_types::{InstructionSeq, MachineName},
+    protocol::{StandardOutput, StandardOutputStream},
+};
+use futures_util::future::{self, Either};
+use std::{
+    future::Future,
+    pin::pin,
+    time::{Duration, Instant},
+};
+use tokio_util::sync::CancellationToken;
+
+/// The collected output and result of an instruction, as returned by
+/// [`ForeverVMClient::exec_and_wait`](super::ForeverVMClient::exec_and_wait).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecOutput {
+    pub machine_name: MachineName,
+    pub instruction_seq: InstructionSeq,
+    /// Output chunks from both streams, in the order they were produced.
+    pub output: Vec<StandardOutput>,
+    pub result: ExecResult,
+}
+
+impl ExecOutput {
+    /// All stdout output, concatenated.
+    pub fn stdout(&self) -> String {
+        self.collect_stream(StandardOutputStream::Stdout)
+    }
+
+    /// All stderr output, concatenated.
+    pub fn stderr(&self) -> String {
+        self.collect_stream(StandardOutputStream::Stderr)
+    }
+
+    /// Returns true if the instruction didn't complete successfully, e.g. because it raised
+    /// an exception or timed out.
+    pub fn is_error(&self) -> bool {
+        !self.result.is_ok()
+    }
+
+    /// How long the instruction ran on the machine.
+    pub fn runtime(&self) -> Duration {
+        Duration::from_millis(self.result.runtime_ms)
+    }
+
+    fn collect_stream(&self, stream: StandardOutputStream) -> String {
+        self.output
+            .iter()
+            .filter(|chunk| chunk.stream == stream)
+            .map(|chunk| chunk.data.as_str())
+            .collect()
+    }
+}
+
+/// Options for [`ForeverVMClient::exec_batch`](super::ForeverVMClient::exec_batch).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BatchOptions {
+    /// Don't run any further instructions after one fails.
+    pub stop_on_error: bool,
+}
+
+/// How far a batch got before a request failed, as carried by [`ClientError::Batch`].
+#[derive(thiserror::Error, Debug)]
+#[error("{error} ({} instructions finished, {} still pending)", outputs.len(), pending.len())]
+pub struct BatchError {
+    /// The error that ended the batch.
+    pub error: ClientError,
+    /// The outputs of the instructions that finished, in order.
+    pub outputs: Vec<ExecOutput>,
+    /// Instructions that were submitted but whose output wasn't collected. They are still
+    /// queued or running on the machine.
+    pub pending: Vec<InstructionSeq>,
+}
+
+/// Client-side limits on how long to wait for an instruction. These complement the
+/// instruction's own `timeout_seconds`, which only limits how long it runs on the machine.
+///
+/// When the deadline passes or the token is cancelled after an instruction was sent, the
+/// SDK interrupts its machine with
+/// [`ForeverVMClient::interrupt_machine`](super::ForeverVMClient::interrupt_machine), unless
+/// the instruction is known to have finished, and the call fails with
+/// [`ClientError::DeadlineExceeded`] or [`ClientError::Cancelled`]. Options that have
+/// already stopped fail the call before anything is sent.
+#[derive(Debug, Clone, Default)]
+pub struct ExecOptions {
+    pub deadline: Option<Instant>,
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl ExecOptions {
+    /// Options with a deadline `timeout` from now.
+    pub fn timeout(timeout: Duration) -> Self {
+        Self {
+            deadline: Some(Instant::now() + timeout),
+            cancellation: None,
+        }
+    }
+
+    /// Options that stop waiting when `token` is cancelled.
+    pub fn cancellation(token: CancellationToken) -> Self {
+        Self {
+            deadline: None,
+            cancellation: Some(token),
+        }
+    }
+
+    /// Runs `future` to completion, unless the deadline passes or the token is cancelled
+    /// first.
+    pub(crate) async fn run<T>(&self, future: impl Future<Output = Result<T>>) -> Result<T> {
+        match future::select(pin!(future), pin!(self.stopped())).await {
+            Either::Left((result, _)) => result,
+            Either::Right((err, _)) => Err(err),
+        }
+    }
+
+    /// Fails if the deadline has already passed or the token is already cancelled, so that
+    /// a call which can't succeed sends nothing.
+    pub(crate) fn check(&self) -> Result<()> {
+        if self
+            .cancellation
+            .as_ref()
+            .is_some_and(|token| token.is_cancelled())
+        {
+            return Err(ClientError::Cancelled);
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| deadline <= Instant::now())
+        {
+            return Err(ClientError::DeadlineExceeded);
+        }
+        Ok(())
+    }
+
+    /// Resolves with the error to report once the deadline passes or the token is cancelled.
+    /// Never resolves if neither is set.
+    async fn stopped(&self) -> ClientError {
+        let deadline = async {
+            match self.deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+                None => future::pending().await,
+            }
+        };
+        let cancelled = async {
+            match &self.cancellation {
+                Some(token) => token.cancelled().await,
+                None => future::pending().await,
+            }
+        };
+
+        match future::select(pin!(deadline), pin!(cancelled)).await {
+            Either::Left(_) => ClientError::DeadlineExceeded,
+            Either::Right(_) => ClientError::Cancelled,
+        }
+    }
+}
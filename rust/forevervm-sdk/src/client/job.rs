@@ -0,0 +1,81 @@
+//! Detached instructions that outlive the process that started them.
+
+use super::{error::Result, ForeverVMClient};
+use crate::api::{
+    api_types::ApiInstruction,
+    id_types::{InstructionSeq, MachineName},
+    protocol::MessageFromServer,
+};
+use futures_util::Stream;
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, pin::Pin, str::FromStr, time::Duration};
+
+/// A reference to an instruction started with [`ForeverVMClient::submit_job`].
+///
+/// A handle is plain data: it can be serialized, or written as `<machine>/<seq>` with
+/// [`Display`] and read back with [`FromStr`], and used from any process with a client for
+/// the same account.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JobHandle {
+    pub machine: MachineName,
+    pub seq: InstructionSeq,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("Invalid job `{0}`; expected `<machine>/<seq>`")]
+pub struct InvalidJobHandle(String);
+
+impl JobHandle {
+    /// Fetches the job's current status, and its result if it has finished.
+    pub async fn poll(&self, client: &ForeverVMClient) -> Result<ApiInstruction> {
+        client.get_instruction(&self.machine, self.seq).await
+    }
+
+    /// Waits until the job has finished. See [`ForeverVMClient::wait_instruction`].
+    pub async fn wait(
+        &self,
+        client: &ForeverVMClient,
+        timeout: Duration,
+    ) -> Result<ApiInstruction> {
+        client
+            .wait_instruction(&self.machine, self.seq, timeout)
+            .await
+    }
+
+    /// Streams the job's output from the start, followed by its result once it finishes.
+    /// See [`ForeverVMClient::exec_result_stream`].
+    pub async fn stream(
+        &self,
+        client: &ForeverVMClient,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<MessageFromServer>> + Send>>> {
+        client.exec_result_stream(&self.machine, self.seq).await
+    }
+}
+
+impl Display for JobHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.machine, self.seq)
+    }
+}
+
+impl FromStr for JobHandle {
+    type Err = InvalidJobHandle;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || InvalidJobHandle(s.to_string());
+
+        let (machine, seq) = s.trim().rsplit_once('/').ok_or_else(invalid)?;
+        if machine.is_empty() {
+            return Err(invalid());
+        }
+        let seq = seq.parse::<i64>().map_err(|_| invalid())?;
+        if seq < 0 {
+            return Err(invalid());
+        }
+
+        Ok(JobHandle {
+            machine: MachineName(machine.to_string()),
+            seq: InstructionSeq(seq),
+        })
+    }
+}
@@ -0,0 +1,202 @@
+use super::{
+    error::Result,
+    exec::{BatchOptions, ExecOptions, ExecOutput},
+    job::JobHandle,
+    repl::ReplConnection,
+    ForeverVMClient,
+};
+use crate::api::{
+    api_types::{ApiExecResponse, ApiExecResultResponse, ApiInstruction, ApiMachine, Instruction},
+    http_api::{ListInstructionsRequest, ListInstructionsResponse},
+    id_types::{InstructionSeq, MachineName},
+    protocol::{MessageFromServer, StandardOutput},
+};
+use futures_util::Stream;
+use std::{collections::HashMap, pin::Pin, time::Duration};
+
+/// A machine on a [`ForeverVMClient`], so that the machine name doesn't need to be passed
+/// to every call. Created with [`ForeverVMClient::machine`].
+#[derive(Debug, Clone)]
+pub struct MachineHandle<'a> {
+    client: &'a ForeverVMClient,
+    name: MachineName,
+}
+
+impl<'a> MachineHandle<'a> {
+    pub(crate) fn new(client: &'a ForeverVMClient, name: MachineName) -> Self {
+        Self { client, name }
+    }
+
+    pub fn name(&self) -> &MachineName {
+        &self.name
+    }
+
+    pub fn client(&self) -> &'a ForeverVMClient {
+        self.client
+    }
+
+    /// Starts running an instruction over HTTP. See [`ForeverVMClient::exec_instruction`].
+    pub async fn exec(&self, instruction: Instruction) -> Result<ApiExecResponse> {
+        self.client.exec_instruction(&self.name, instruction).await
+    }
+
+    /// Runs an instruction over HTTP and waits for it to finish, collecting its output.
+    /// See [`ForeverVMClient::exec_and_wait`].
+    pub async fn exec_and_wait(&self, instruction: Instruction) -> Result<ExecOutput> {
+        self.client.exec_and_wait(&self.name, instruction).await
+    }
+
+    /// Like [`MachineHandle::exec_and_wait`], but also calls `on_output` with each chunk
+    /// of output as it arrives.
+    pub async fn exec_and_wait_with(
+        &self,
+        instruction: Instruction,
+        on_output: impl FnMut(&StandardOutput),
+    ) -> Result<ExecOutput> {
+        self.client
+            .exec_and_wait_with(&self.name, instruction, on_output)
+            .await
+    }
+
+    /// Like [`MachineHandle::exec_and_wait_with`], but gives up when `options`' deadline
+    /// passes or its token is cancelled. See
+    /// [`ForeverVMClient::exec_and_wait_with_options`].
+    pub async fn exec_and_wait_with_options(
+        &self,
+        instruction: Instruction,
+        options: &ExecOptions,
+        on_output: impl FnMut(&StandardOutput),
+    ) -> Result<ExecOutput> {
+        self.client
+            .exec_and_wait_with_options(&self.name, instruction, options, on_output)
+            .await
+    }
+
+    /// Interrupts the machine's running and pending instructions. See
+    /// [`ForeverVMClient::interrupt_machine`].
+    pub async fn interrupt(&self) -> Result<ApiExecResponse> {
+        self.client.interrupt_machine(&self.name).await
+    }
+
+    /// Runs several instructions in order. See [`ForeverVMClient::exec_batch`].
+    pub async fn exec_batch(
+        &self,
+        instructions: Vec<Instruction>,
+        options: BatchOptions,
+    ) -> Result<Vec<ExecOutput>> {
+        self.client
+            .exec_batch(&self.name, instructions, options)
+            .await
+    }
+
+    /// Starts an instruction as a detached job. See [`ForeverVMClient::submit_job`].
+    pub async fn submit_job(&self, instruction: Instruction) -> Result<JobHandle> {
+        self.client.submit_job(instruction, Some(&self.name)).await
+    }
+
+    pub async fn result(&self, instruction: InstructionSeq) -> Result<ApiExecResultResponse> {
+        self.client.exec_result(&self.name, instruction).await
+    }
+
+    pub async fn stream(
+        &self,
+        instruction: InstructionSeq,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<MessageFromServer>> + Send>>> {
+        self.client
+            .exec_result_stream(&self.name, instruction)
+            .await
+    }
+
+    /// Fetches one page of the machine's instruction history. See
+    /// [`ForeverVMClient::list_instructions`].
+    pub async fn list_instructions(
+        &self,
+        options: ListInstructionsRequest,
+    ) -> Result<ListInstructionsResponse> {
+        self.client.list_instructions(&self.name, options).await
+    }
+
+    /// Streams the machine's instruction history. See [`ForeverVMClient::instructions`].
+    pub fn instructions(
+        &self,
+        query: ListInstructionsRequest,
+    ) -> Pin<Box<dyn Stream<Item = Result<ApiInstruction>> + Send + '_>> {
+        self.client.instructions(&self.name, query)
+    }
+
+    /// Fetches a single instruction. See [`ForeverVMClient::get_instruction`].
+    pub async fn instruction(&self, instruction: InstructionSeq) -> Result<ApiInstruction> {
+        self.client.get_instruction(&self.name, instruction).await
+    }
+
+    /// Waits until an instruction has finished. See [`ForeverVMClient::wait_instruction`].
+    pub async fn wait_instruction(
+        &self,
+        instruction: InstructionSeq,
+        timeout: Duration,
+    ) -> Result<ApiInstruction> {
+        self.client
+            .wait_instruction(&self.name, instruction, timeout)
+            .await
+    }
+
+    pub async fn repl(&self) -> Result<ReplConnection> {
+        self.client.repl(&self.name).await
+    }
+
+    /// Fetches the machine's current state from the server.
+    pub async fn info(&self) -> Result<ApiMachine> {
+        self.client.get_machine(&self.name).await
+    }
+
+    /// Fetches the machine's tags.
+    pub async fn tags(&self) -> Result<HashMap<String, String>> {
+        Ok(self.info().await?.tags)
+    }
+
+    /// Parks the machine. See [`ForeverVMClient::suspend_machine`].
+    pub async fn suspend(&self) -> Result<ApiMachine> {
+        self.client.suspend_machine(&self.name).await
+    }
+
+    /// Starts the machine and waits until it is running. See
+    /// [`ForeverVMClient::resume_machine`].
+    pub async fn resume(&self, timeout: Duration) -> Result<ApiMachine> {
+        self.client.resume_machine(&self.name, timeout).await
+    }
+
+    /// Waits until the machine has no pending instruction. See
+    /// [`ForeverVMClient::wait_idle`].
+    pub async fn wait_idle(&self, timeout: Duration) -> Result<ApiMachine> {
+        self.client.wait_idle(&self.name, timeout).await
+    }
+
+    /// Waits until the machine is running. See [`ForeverVMClient::wait_running`].
+    pub async fn wait_running(&self, timeout: Duration) -> Result<ApiMachine> {
+        self.client.wait_running(&self.name, timeout).await
+    }
+
+    /// Changes the machine's memory size. See [`ForeverVMClient::resize_machine`].
+    pub async fn resize(&self, memory_mb: u32) -> Result<ApiMachine> {
+        self.client.resize_machine(&self.name, memory_mb).await
+    }
+
+    /// Sets and removes tags atomically. See [`ForeverVMClient::update_tags`].
+    pub async fn update_tags(
+        &self,
+        set: HashMap<String, String>,
+        remove: Vec<String>,
+    ) -> Result<ApiMachine> {
+        self.client.update_tags(&self.name, set, remove).await
+    }
+
+    /// Pushes back the machine's expiry time. See [`ForeverVMClient::extend_expiry`].
+    pub async fn extend_expiry(&self, by: Duration) -> Result<ApiMachine> {
+        self.client.extend_expiry(&self.name, by).await
+    }
+
+    /// Permanently deletes the machine. See [`ForeverVMClient::delete_machine`].
+    pub async fn delete(&self) -> Result<()> {
+        self.client.delete_machine(&self.name).await
+    }
+}
@@ -0,0 +1,93 @@
+//! Hooks for inspecting and modifying requests made by [`ForeverVMClient`](super::ForeverVMClient).
+
+use super::error::{ClientError, Result};
+use futures_util::future::BoxFuture;
+use reqwest::{header::HeaderMap, Method, StatusCode, Url};
+use std::time::Duration;
+
+/// The kind of connection a request is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestKind {
+    /// A regular JSON API request.
+    Http,
+    /// A long-lived HTTP request with a newline-delimited JSON response body.
+    Stream,
+    /// The HTTP handshake of a REPL WebSocket connection.
+    WebSocket,
+}
+
+/// A request about to be sent. Middleware may modify any of its fields.
+///
+/// By the time middleware sees the request, `headers` already contains the SDK's default
+/// headers, including `Authorization`.
+#[derive(Debug, Clone)]
+pub struct ClientRequest {
+    pub kind: RequestKind,
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    /// The JSON-encoded request body, if any. Always `None` for WebSocket requests.
+    pub body: Option<Vec<u8>>,
+}
+
+/// The outcome of a request, as seen by [`Middleware::on_response`].
+#[derive(Debug, Clone)]
+pub struct ClientResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    /// Time from sending the request to receiving the response headers.
+    pub elapsed: Duration,
+}
+
+/// A canned response used by middleware to answer a request without sending it.
+#[derive(Debug, Clone)]
+pub struct ShortCircuit {
+    pub status: StatusCode,
+    pub body: Vec<u8>,
+}
+
+impl ShortCircuit {
+    /// A response with the given status and a JSON-encoded body.
+    pub fn json<T: serde::Serialize>(status: StatusCode, body: &T) -> Result<Self> {
+        Ok(Self {
+            status,
+            body: serde_json::to_vec(body)?,
+        })
+    }
+}
+
+/// What the client should do after a middleware has seen a request.
+#[derive(Debug)]
+pub enum RequestAction {
+    /// Pass the request on to the next middleware, and then to the server.
+    Continue,
+    /// Skip the remaining middleware and the server, and use this response instead.
+    ///
+    /// WebSocket connections cannot be answered locally; short-circuiting one fails the
+    /// connection with the given status.
+    Respond(ShortCircuit),
+}
+
+/// Request/response hooks, applied in the order they were added to the client.
+///
+/// Returning an error from [`Middleware::on_request`] aborts the request with that error.
+pub trait Middleware: Send + Sync {
+    fn on_request<'a>(
+        &'a self,
+        request: &'a mut ClientRequest,
+    ) -> BoxFuture<'a, Result<RequestAction>> {
+        let _ = request;
+        Box::pin(async { Ok(RequestAction::Continue) })
+    }
+
+    /// Called once the response headers have been received (or the WebSocket handshake
+    /// has completed), including for non-success statuses and short-circuited requests.
+    fn on_response(&self, request: &ClientRequest, response: &ClientResponse) {
+        let _ = (request, response);
+    }
+
+    /// Called when a request fails before a response is received.
+    fn on_error(&self, request: &ClientRequest, error: &ClientError) {
+        let _ = (request, error);
+    }
+}
@@ -1,45 +1,109 @@
-use crate::{
-    api::{
-        api_types::{ApiExecRequest, ApiExecResponse, ApiExecResultResponse, Instruction},
-        http_api::{
-            CreateMachineRequest, CreateMachineResponse, ListMachinesRequest, ListMachinesResponse,
-            WhoamiResponse,
-        },
-        id_types::{InstructionSeq, MachineName},
-        protocol::MessageFromServer,
-        token::ApiToken,
+use crate::api::{
+    api_types::{
+        ApiExecRequest, ApiExecResponse, ApiExecResultResponse, ApiInstruction, ApiMachine,
+        Instruction, MachineEvent,
     },
-    util::get_runner,
+    http_api::{
+        CreateMachineRequest, CreateMachineResponse, ExtendExpiryRequest, ExtendExpiryResponse,
+        GetInstructionResponse, GetMachineResponse, ListInstructionsRequest,
+        ListInstructionsResponse, ListMachinesRequest, ListMachinesResponse, MachineEventsRequest,
+        ResizeMachineRequest, ResizeMachineResponse, ResumeMachineResponse, SuspendMachineResponse,
+        UpdateTagsRequest, UpdateTagsResponse, WhoamiResponse,
+    },
+    id_types::{InstructionSeq, MachineName},
+    protocol::{MessageFromServer, MessageLevel, StandardOutput},
+    token::ApiToken,
 };
+use builder::ForeverVMClientBuilder;
 use error::{ClientError, Result};
+use exec::{BatchError, BatchOptions, ExecOptions, ExecOutput};
 use futures_util::{Stream, StreamExt};
-use repl::ReplConnection;
-use reqwest::{
-    header::{HeaderMap, HeaderValue},
-    Client, Method, Response, Url,
+use job::JobHandle;
+use machine::MachineHandle;
+use middleware::{
+    ClientRequest, ClientResponse, Middleware, RequestAction, RequestKind, ShortCircuit,
 };
+use repl::ReplConnection;
+use reqwest::{header::HeaderMap, Method, StatusCode, Url};
 use serde::{de::DeserializeOwned, Serialize};
-use std::pin::Pin;
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use transport::{
+    HttpResponse, HttpTransport, ReqwestTransport, SocketTransport, TungsteniteTransport,
+};
+use typed_socket::{WebSocketRecv, WebSocketSend};
+use util::default_headers;
 
+pub mod builder;
 pub mod error;
+pub mod exec;
+pub mod job;
+pub mod machine;
+pub mod middleware;
+pub mod proxy;
 pub mod repl;
+pub mod syntax;
+pub mod tls;
+pub mod transport;
 pub mod typed_socket;
 pub mod util;
 
+pub use tokio_util::sync::CancellationToken;
+
+/// Delay before the first re-fetch of a machine or instruction while waiting for it to
+/// change state. The delay doubles after each fetch, up to `MAX_POLL_INTERVAL`.
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long to spend asking the server to interrupt an instruction after an
+/// [`ExecOptions`] deadline or cancellation.
+const INTERRUPT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The instruction sent along with an interrupt. The exec endpoint always queues an
+/// instruction, so interrupting means replacing whatever is running with one that does
+/// nothing.
+const INTERRUPT_CODE: &str = "pass";
+
+#[derive(Clone)]
 pub struct ForeverVMClient {
     api_base: Url,
-    client: Client,
     token: ApiToken,
+    http: Arc<dyn HttpTransport>,
+    socket: Arc<dyn SocketTransport>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    #[cfg(feature = "validate-syntax")]
+    validate_syntax: bool,
+}
+
+impl Debug for ForeverVMClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ForeverVMClient")
+            .field("api_base", &self.api_base)
+            .field("token", &self.token)
+            .field("middleware", &self.middleware.len())
+            .finish()
+    }
 }
 
-async fn parse_error(response: Response) -> Result<ClientError> {
-    let code = response.status().as_u16();
+async fn parse_error(response: HttpResponse) -> Result<ClientError> {
+    let code = response.status.as_u16();
     let message = response.text().await?;
 
+    Err(error_from_body(code, message))
+}
+
+fn error_from_body(code: u16, message: String) -> ClientError {
     if let Ok(err) = serde_json::from_str(&message) {
-        Err(ClientError::ApiError(err))
+        ClientError::ApiError(err)
     } else {
-        Err(ClientError::ServerResponseError { code, message })
+        ClientError::ServerResponseError { code, message }
     }
 }
 
@@ -48,23 +112,103 @@ impl ForeverVMClient {
         Self {
             api_base,
             token,
-            client: Client::new(),
+            http: Arc::new(ReqwestTransport::default()),
+            socket: Arc::new(TungsteniteTransport::default()),
+            middleware: Vec::new(),
+            #[cfg(feature = "validate-syntax")]
+            validate_syntax: false,
         }
     }
 
+    /// Returns a builder for configuring connection settings such as an HTTP proxy, TLS,
+    /// request middleware, or custom transports.
+    pub fn builder(api_base: Url, token: ApiToken) -> ForeverVMClientBuilder {
+        ForeverVMClientBuilder::new(api_base, token)
+    }
+
     pub fn server_url(&self) -> &Url {
         &self.api_base
     }
 
-    fn headers() -> HeaderMap {
-        let mut headers = HeaderMap::new();
-        headers.insert("x-forevervm-sdk", HeaderValue::from_static("rust"));
+    /// Returns a handle for making calls against a single machine.
+    pub fn machine(&self, machine_name: &MachineName) -> MachineHandle<'_> {
+        MachineHandle::new(self, machine_name.clone())
+    }
+
+    fn new_request(&self, kind: RequestKind, method: Method, url: Url) -> Result<ClientRequest> {
+        Ok(ClientRequest {
+            kind,
+            method,
+            url,
+            headers: default_headers(&self.token)?,
+            body: None,
+        })
+    }
+
+    /// Runs the request through each middleware in turn. Returns the response to use
+    /// instead of sending the request, if a middleware short-circuited it.
+    async fn run_middleware(&self, request: &mut ClientRequest) -> Result<Option<ShortCircuit>> {
+        for middleware in &self.middleware {
+            if let RequestAction::Respond(response) = middleware.on_request(request).await? {
+                let info = ClientResponse {
+                    status: response.status,
+                    headers: HeaderMap::new(),
+                    elapsed: Default::default(),
+                };
+                self.notify_response(request, &info);
+                return Ok(Some(response));
+            }
+        }
+
+        Ok(None)
+    }
 
-        if let Some(val) = get_runner().and_then(|v| HeaderValue::from_str(&v).ok()) {
-            headers.insert("x-forevervm-runner", val);
+    fn notify_response(&self, request: &ClientRequest, response: &ClientResponse) {
+        for middleware in &self.middleware {
+            middleware.on_response(request, response);
         }
+    }
 
-        headers
+    fn notify_error(&self, request: &ClientRequest, error: &ClientError) {
+        for middleware in &self.middleware {
+            middleware.on_error(request, error);
+        }
+    }
+
+    /// Sends an HTTP request through the middleware chain. Non-success statuses are
+    /// returned as errors.
+    async fn send(&self, mut request: ClientRequest) -> Result<HttpResponse> {
+        if let Some(short_circuit) = self.run_middleware(&mut request).await? {
+            let response = HttpResponse::from_bytes(short_circuit.status, short_circuit.body);
+            if !response.status.is_success() {
+                return Err(parse_error(response).await?);
+            }
+            return Ok(response);
+        }
+
+        let start = Instant::now();
+        let response = match self.http.execute(request.clone()).await {
+            Ok(response) => response,
+            Err(err) => {
+                self.notify_error(&request, &err);
+                return Err(err);
+            }
+        };
+
+        self.notify_response(
+            &request,
+            &ClientResponse {
+                status: response.status,
+                headers: response.headers.clone(),
+                elapsed: start.elapsed(),
+            },
+        );
+
+        if !response.status.is_success() {
+            return Err(parse_error(response).await?);
+        }
+
+        Ok(response)
     }
 
     pub async fn repl(&self, machine_name: &MachineName) -> Result<ReplConnection> {
@@ -84,7 +228,46 @@ impl ForeverVMClient {
         }
 
         let url = base_url.join(&format!("/v1/machine/{machine_name}/repl"))?;
-        ReplConnection::new(url, self.token.clone()).await
+        let mut request = self.new_request(RequestKind::WebSocket, Method::GET, url)?;
+        if let Some(short_circuit) = self.run_middleware(&mut request).await? {
+            return Err(error_from_body(
+                short_circuit.status.as_u16(),
+                String::from_utf8_lossy(&short_circuit.body).into_owned(),
+            ));
+        }
+
+        let start = Instant::now();
+        let connection = async {
+            let (sink, stream) = self.socket.connect(request.clone()).await?;
+            let repl = ReplConnection::open(
+                WebSocketSend::new(sink),
+                WebSocketRecv::new(stream),
+                Some(self.clone()),
+            )
+            .await?;
+            #[cfg(feature = "validate-syntax")]
+            let repl = repl.validate_syntax(self.validate_syntax);
+            Ok(repl)
+        }
+        .await;
+
+        match connection {
+            Ok(repl) => {
+                self.notify_response(
+                    &request,
+                    &ClientResponse {
+                        status: StatusCode::SWITCHING_PROTOCOLS,
+                        headers: HeaderMap::new(),
+                        elapsed: start.elapsed(),
+                    },
+                );
+                Ok(repl)
+            }
+            Err(err) => {
+                self.notify_error(&request, &err);
+                Err(err)
+            }
+        }
     }
 
     async fn post_request<Request: Serialize, Response: DeserializeOwned>(
@@ -92,47 +275,55 @@ impl ForeverVMClient {
         path: &str,
         request: Request,
     ) -> Result<Response> {
-        let url = self.api_base.join(&format!("/v1{}", path))?;
-        let response = self
-            .client
-            .request(Method::POST, url)
-            .headers(ForeverVMClient::headers())
-            .bearer_auth(self.token.to_string())
-            .json(&request)
-            .send()
-            .await?;
+        let response = self.post(path, request).await?;
+        response.json().await
+    }
 
-        if !response.status().is_success() {
-            return Err(parse_error(response).await?);
-        }
+    /// Like [`ForeverVMClient::post_request`], for endpoints whose response body is unused.
+    async fn post<Request: Serialize>(&self, path: &str, request: Request) -> Result<HttpResponse> {
+        let url = self.api_base.join(&format!("/v1{}", path))?;
+        let mut client_request = self.new_request(RequestKind::Http, Method::POST, url)?;
+        client_request.body = Some(serde_json::to_vec(&request)?);
 
-        Ok(response.json().await?)
+        self.send(client_request).await
     }
 
     async fn get_request<Response: DeserializeOwned>(&self, path: &str) -> Result<Response> {
         let url = self.api_base.join(&format!("/v1{}", path))?;
-        let response = self
-            .client
-            .request(Method::GET, url)
-            .headers(ForeverVMClient::headers())
-            .bearer_auth(self.token.to_string())
-            .send()
-            .await?;
+        let request = self.new_request(RequestKind::Http, Method::GET, url)?;
 
-        if !response.status().is_success() {
-            return Err(parse_error(response).await?);
-        }
-
-        Ok(response.json().await?)
+        let response = self.send(request).await?;
+        response.json().await
     }
 
     pub async fn create_machine(
         &self,
         options: CreateMachineRequest,
     ) -> Result<CreateMachineResponse> {
+        if options.ttl_seconds.is_some() && options.expires_at.is_some() {
+            return Err(ClientError::Other(
+                "Only one of `ttl_seconds` and `expires_at` may be set".to_string(),
+            ));
+        }
+
         self.post_request("/machine/new", options).await
     }
 
+    /// Pushes back a machine's expiry time, returning the machine with its new `expires_at`.
+    pub async fn extend_expiry(
+        &self,
+        machine_name: &MachineName,
+        by: Duration,
+    ) -> Result<ApiMachine> {
+        let request = ExtendExpiryRequest {
+            extend_by_seconds: by.as_secs(),
+        };
+        let response: ExtendExpiryResponse = self
+            .post_request(&format!("/machine/{machine_name}/extend"), request)
+            .await?;
+        Ok(response.machine)
+    }
+
     pub async fn list_machines(
         &self,
         options: ListMachinesRequest,
@@ -140,11 +331,195 @@ impl ForeverVMClient {
         self.post_request("/machine/list", options).await
     }
 
+    /// Parks a machine, releasing its resources until it is next used. Its state is kept.
+    pub async fn suspend_machine(&self, machine_name: &MachineName) -> Result<ApiMachine> {
+        let response: SuspendMachineResponse = self
+            .post_request(&format!("/machine/{machine_name}/suspend"), json!({}))
+            .await?;
+        Ok(response.machine)
+    }
+
+    /// Starts a suspended machine ahead of time, so that the next instruction doesn't wait
+    /// for it to boot. Waits until the machine is running, or fails with
+    /// [`ClientError::Timeout`] after `timeout`.
+    pub async fn resume_machine(
+        &self,
+        machine_name: &MachineName,
+        timeout: Duration,
+    ) -> Result<ApiMachine> {
+        let response: ResumeMachineResponse = self
+            .post_request(&format!("/machine/{machine_name}/resume"), json!({}))
+            .await?;
+        if response.machine.running {
+            return Ok(response.machine);
+        }
+
+        self.poll_machine(machine_name, timeout, |machine| machine.running)
+            .await
+    }
+
+    /// Waits until the machine has no pending instruction, so that new work won't queue
+    /// behind a running one. Fails with [`ClientError::Timeout`] after `timeout`.
+    pub async fn wait_idle(
+        &self,
+        machine_name: &MachineName,
+        timeout: Duration,
+    ) -> Result<ApiMachine> {
+        self.poll_machine(machine_name, timeout, |machine| {
+            !machine.has_pending_instruction
+        })
+        .await
+    }
+
+    /// Waits until the machine is running. Unlike [`ForeverVMClient::resume_machine`], this
+    /// doesn't start the machine. Fails with [`ClientError::Timeout`] after `timeout`.
+    pub async fn wait_running(
+        &self,
+        machine_name: &MachineName,
+        timeout: Duration,
+    ) -> Result<ApiMachine> {
+        self.poll_machine(machine_name, timeout, |machine| machine.running)
+            .await
+    }
+
+    /// Fetches the machine with exponential backoff until `condition` holds.
+    async fn poll_machine(
+        &self,
+        machine_name: &MachineName,
+        timeout: Duration,
+        condition: impl Fn(&ApiMachine) -> bool,
+    ) -> Result<ApiMachine> {
+        poll_until(timeout, || self.get_machine(machine_name), condition).await
+    }
+
+    /// Changes a machine's memory size, returning the machine with its new resources.
+    pub async fn resize_machine(
+        &self,
+        machine_name: &MachineName,
+        memory_mb: u32,
+    ) -> Result<ApiMachine> {
+        let request = ResizeMachineRequest { memory_mb };
+        let response: ResizeMachineResponse = self
+            .post_request(&format!("/machine/{machine_name}/resize"), request)
+            .await?;
+        Ok(response.machine)
+    }
+
+    /// Sets and removes tags on a machine in a single atomic update, returning the machine
+    /// with its new tags. A key may not be both set and removed.
+    pub async fn update_tags(
+        &self,
+        machine_name: &MachineName,
+        set: HashMap<String, String>,
+        remove: Vec<String>,
+    ) -> Result<ApiMachine> {
+        if let Some(key) = remove.iter().find(|key| set.contains_key(*key)) {
+            return Err(ClientError::Other(format!(
+                "Tag `{key}` cannot be both set and removed"
+            )));
+        }
+
+        let request = UpdateTagsRequest { set, remove };
+        let response: UpdateTagsResponse = self
+            .post_request(&format!("/machine/{machine_name}/tags"), request)
+            .await?;
+        Ok(response.machine)
+    }
+
+    /// Permanently deletes a machine, discarding its state.
+    pub async fn delete_machine(&self, machine_name: &MachineName) -> Result<()> {
+        self.post(&format!("/machine/{machine_name}/delete"), json!({}))
+            .await?;
+        Ok(())
+    }
+
+    /// Lists all machines matching `query`, fetching further pages as the stream is consumed.
+    /// `query.cursor` may be set to resume from a previous page.
+    pub fn machines(
+        &self,
+        query: ListMachinesRequest,
+    ) -> Pin<Box<dyn Stream<Item = Result<ApiMachine>> + Send + '_>> {
+        paginate(query.cursor.clone(), move |cursor| {
+            let query = ListMachinesRequest {
+                cursor,
+                ..query.clone()
+            };
+            async move {
+                let page = self.list_machines(query).await?;
+                Ok((page.machines, page.next_cursor))
+            }
+        })
+    }
+
+    /// Fetches one page of a machine's instruction history.
+    pub async fn list_instructions(
+        &self,
+        machine_name: &MachineName,
+        options: ListInstructionsRequest,
+    ) -> Result<ListInstructionsResponse> {
+        self.post_request(&format!("/machine/{machine_name}/instructions"), options)
+            .await
+    }
+
+    /// Lists a machine's instruction history, fetching further pages as the stream is
+    /// consumed. `query.cursor` may be set to resume from a previous page.
+    pub fn instructions<'a>(
+        &'a self,
+        machine_name: &'a MachineName,
+        query: ListInstructionsRequest,
+    ) -> Pin<Box<dyn Stream<Item = Result<ApiInstruction>> + Send + 'a>> {
+        paginate(query.cursor.clone(), move |cursor| {
+            let query = ListInstructionsRequest {
+                cursor,
+                ..query.clone()
+            };
+            async move {
+                let page = self.list_instructions(machine_name, query).await?;
+                Ok((page.instructions, page.next_cursor))
+            }
+        })
+    }
+
+    /// Fetches a single instruction's status, and its result once it has finished.
+    pub async fn get_instruction(
+        &self,
+        machine_name: &MachineName,
+        instruction: InstructionSeq,
+    ) -> Result<ApiInstruction> {
+        let response: GetInstructionResponse = self
+            .get_request(&format!(
+                "/machine/{machine_name}/instructions/{instruction}"
+            ))
+            .await?;
+        Ok(response.instruction)
+    }
+
+    /// Waits until an instruction has finished, without holding a connection open while it
+    /// runs. Fails with [`ClientError::Timeout`] after `timeout`.
+    pub async fn wait_instruction(
+        &self,
+        machine_name: &MachineName,
+        instruction: InstructionSeq,
+        timeout: Duration,
+    ) -> Result<ApiInstruction> {
+        poll_until(
+            timeout,
+            || self.get_instruction(machine_name, instruction),
+            |instruction| instruction.is_finished(),
+        )
+        .await
+    }
+
     pub async fn exec_instruction(
         &self,
         machine_name: &MachineName,
         instruction: Instruction,
     ) -> Result<ApiExecResponse> {
+        #[cfg(feature = "validate-syntax")]
+        if self.validate_syntax {
+            syntax::validate_syntax(&instruction.code)?;
+        }
+
         let request = ApiExecRequest {
             instruction,
             interrupt: false,
@@ -154,6 +529,299 @@ impl ForeverVMClient {
             .await
     }
 
+    /// Interrupts the instruction that the machine is running, along with any that is
+    /// pending. This sends a no-op instruction with the exec request's `interrupt` flag set,
+    /// which is the only way the server offers to stop an instruction; the response
+    /// describes that no-op instruction.
+    pub async fn interrupt_machine(&self, machine_name: &MachineName) -> Result<ApiExecResponse> {
+        let request = ApiExecRequest {
+            instruction: Instruction::new(INTERRUPT_CODE),
+            interrupt: true,
+        };
+
+        self.post_request(&format!("/machine/{machine_name}/exec"), request)
+            .await
+    }
+
+    /// Starts running code over HTTP. If no machine is given, a new one is created first;
+    /// its name is returned in the response's `machine` field.
+    pub async fn exec(
+        &self,
+        instruction: Instruction,
+        machine_name: Option<&MachineName>,
+    ) -> Result<ApiExecResponse> {
+        let machine_name = match machine_name {
+            Some(machine_name) => machine_name.clone(),
+            None => {
+                self.create_machine(CreateMachineRequest::default())
+                    .await?
+                    .machine_name
+            }
+        };
+
+        let mut response = self.exec_instruction(&machine_name, instruction).await?;
+        response.machine.get_or_insert(machine_name);
+        Ok(response)
+    }
+
+    /// Like [`ForeverVMClient::exec`], but gives up when `options`' deadline passes or its
+    /// token is cancelled. Options that have already stopped fail without sending anything;
+    /// if they stop while the exec request is in flight, the machine is interrupted in case
+    /// the instruction was queued.
+    pub async fn exec_with_options(
+        &self,
+        instruction: Instruction,
+        machine_name: Option<&MachineName>,
+        options: &ExecOptions,
+    ) -> Result<ApiExecResponse> {
+        options.check()?;
+
+        let machine_name = match machine_name {
+            Some(machine_name) => machine_name.clone(),
+            None => {
+                options
+                    .run(self.create_machine(CreateMachineRequest::default()))
+                    .await?
+                    .machine_name
+            }
+        };
+
+        let result = options
+            .run(self.exec_instruction(&machine_name, instruction))
+            .await;
+        match result {
+            Ok(mut response) => {
+                response.machine.get_or_insert(machine_name);
+                Ok(response)
+            }
+            Err(err) => {
+                if err.is_stopped() {
+                    self.interrupt_after_stop(&machine_name, None).await;
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Starts an instruction as a detached job, returning as soon as the server has accepted
+    /// it. If `machine_name` is `None`, a new machine is created for it.
+    ///
+    /// The returned [`JobHandle`] can be saved and used later, from any process, to check on
+    /// the instruction. Give the instruction a `timeout_seconds` long enough for the work.
+    pub async fn submit_job(
+        &self,
+        instruction: Instruction,
+        machine_name: Option<&MachineName>,
+    ) -> Result<JobHandle> {
+        let response = self.exec(instruction, machine_name).await?;
+        let seq = instruction_seq(&response)?;
+        let machine = response.machine.expect("exec always sets the machine name");
+
+        Ok(JobHandle { machine, seq })
+    }
+
+    /// Runs an instruction over HTTP and waits for it to finish, collecting its output.
+    pub async fn exec_and_wait(
+        &self,
+        machine_name: &MachineName,
+        instruction: Instruction,
+    ) -> Result<ExecOutput> {
+        self.exec_and_wait_with(machine_name, instruction, |_| {})
+            .await
+    }
+
+    /// Like [`ForeverVMClient::exec_and_wait`], but also calls `on_output` with each chunk
+    /// of output as it arrives.
+    pub async fn exec_and_wait_with(
+        &self,
+        machine_name: &MachineName,
+        instruction: Instruction,
+        on_output: impl FnMut(&StandardOutput),
+    ) -> Result<ExecOutput> {
+        self.exec_and_wait_with_options(
+            machine_name,
+            instruction,
+            &ExecOptions::default(),
+            on_output,
+        )
+        .await
+    }
+
+    /// Like [`ForeverVMClient::exec_and_wait_with`], but gives up when `options`' deadline
+    /// passes or its token is cancelled, interrupting the instruction if it has started.
+    pub async fn exec_and_wait_with_options(
+        &self,
+        machine_name: &MachineName,
+        instruction: Instruction,
+        options: &ExecOptions,
+        on_output: impl FnMut(&StandardOutput),
+    ) -> Result<ExecOutput> {
+        let response = self
+            .exec_with_options(instruction, Some(machine_name), options)
+            .await?;
+        let instruction_seq = instruction_seq(&response)?;
+
+        let result = options
+            .run(self.collect_output(machine_name, instruction_seq, on_output))
+            .await;
+        if let Err(err) = &result {
+            if err.is_stopped() {
+                self.interrupt_after_stop(machine_name, Some(instruction_seq))
+                    .await;
+            }
+        }
+        result
+    }
+
+    /// Interrupts the machine after the caller stopped waiting for an instruction on it.
+    /// When the instruction's seq is known, the machine is left alone if that instruction
+    /// has already finished, since the interrupt would hit whatever runs next.
+    ///
+    /// The caller has already been given the stop error, so this is best-effort: failures
+    /// are logged, and the attempt is bounded so that a stalled network can't hold up the
+    /// caller.
+    async fn interrupt_after_stop(
+        &self,
+        machine_name: &MachineName,
+        instruction: Option<InstructionSeq>,
+    ) {
+        let interrupt = async {
+            if let Some(instruction) = instruction {
+                if self
+                    .get_instruction(machine_name, instruction)
+                    .await?
+                    .is_finished()
+                {
+                    return Ok(());
+                }
+            }
+            self.interrupt_machine(machine_name).await.map(|_| ())
+        };
+        match tokio::time::timeout(INTERRUPT_TIMEOUT, interrupt).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => tracing::warn!(?err, %machine_name, "Failed to interrupt machine"),
+            Err(_) => tracing::warn!(%machine_name, "Timed out interrupting machine"),
+        }
+    }
+
+    /// Runs several instructions on a machine in order, returning each one's collected
+    /// output in the same order.
+    ///
+    /// Normally every instruction is submitted up front, so that the machine works through
+    /// them back to back without waiting on a round trip between each. With
+    /// [`BatchOptions::stop_on_error`], each instruction is only submitted once the previous
+    /// one has succeeded, because a queued instruction can't be withdrawn; the batch then
+    /// ends at the first instruction that fails, which is the last one returned.
+    ///
+    /// Batches always run over HTTP. A [`ReplConnection`] only tracks one instruction at a
+    /// time, so it can't queue several.
+    ///
+    /// If a request fails partway through, the error is a [`ClientError::Batch`] holding
+    /// the outputs collected so far and the instructions that were submitted but not
+    /// collected, which keep running on the machine.
+    pub async fn exec_batch(
+        &self,
+        machine_name: &MachineName,
+        instructions: Vec<Instruction>,
+        options: BatchOptions,
+    ) -> Result<Vec<ExecOutput>> {
+        let mut outputs = Vec::with_capacity(instructions.len());
+        let batch_error = |error, outputs, pending| {
+            ClientError::Batch(Box::new(BatchError {
+                error,
+                outputs,
+                pending,
+            }))
+        };
+
+        if options.stop_on_error {
+            for instruction in instructions {
+                let response = self.exec_instruction(machine_name, instruction).await;
+                let instruction_seq = match response.and_then(|response| instruction_seq(&response))
+                {
+                    Ok(instruction_seq) => instruction_seq,
+                    Err(err) => return Err(batch_error(err, outputs, Vec::new())),
+                };
+
+                let output = match self
+                    .collect_output(machine_name, instruction_seq, |_| {})
+                    .await
+                {
+                    Ok(output) => output,
+                    Err(err) => return Err(batch_error(err, outputs, vec![instruction_seq])),
+                };
+                let failed = output.is_error();
+                outputs.push(output);
+                if failed {
+                    break;
+                }
+            }
+            return Ok(outputs);
+        }
+
+        let mut instruction_seqs = Vec::with_capacity(instructions.len());
+        for instruction in instructions {
+            let response = self.exec_instruction(machine_name, instruction).await;
+            match response.and_then(|response| instruction_seq(&response)) {
+                Ok(instruction_seq) => instruction_seqs.push(instruction_seq),
+                Err(err) => return Err(batch_error(err, outputs, instruction_seqs)),
+            }
+        }
+        for (i, &instruction_seq) in instruction_seqs.iter().enumerate() {
+            match self
+                .collect_output(machine_name, instruction_seq, |_| {})
+                .await
+            {
+                Ok(output) => outputs.push(output),
+                Err(err) => return Err(batch_error(err, outputs, instruction_seqs[i..].to_vec())),
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    /// Streams an instruction's output until it finishes.
+    async fn collect_output(
+        &self,
+        machine_name: &MachineName,
+        instruction_seq: InstructionSeq,
+        mut on_output: impl FnMut(&StandardOutput),
+    ) -> Result<ExecOutput> {
+        let mut stream = self
+            .exec_result_stream(machine_name, instruction_seq)
+            .await?;
+        let mut output = Vec::new();
+        while let Some(message) = stream.next().await {
+            match message? {
+                MessageFromServer::Output {
+                    chunk,
+                    instruction_id,
+                } if instruction_id == instruction_seq => {
+                    on_output(&chunk);
+                    output.push(chunk);
+                }
+                MessageFromServer::Result(result) if result.instruction_id == instruction_seq => {
+                    return Ok(ExecOutput {
+                        machine_name: machine_name.clone(),
+                        instruction_seq,
+                        output,
+                        result: result.result,
+                    });
+                }
+                MessageFromServer::Error(err) => return Err(err.into()),
+                MessageFromServer::Message { message, level } => match level {
+                    MessageLevel::Info => tracing::info!("{message}"),
+                    MessageLevel::Warn => tracing::warn!("{message}"),
+                    MessageLevel::Error => tracing::error!("{message}"),
+                },
+                message => tracing::debug!(?message, "Ignoring unexpected message"),
+            }
+        }
+
+        // The stream only ends without a result if the instruction never completed.
+        Err(ClientError::InstructionInterrupted)
+    }
+
     pub async fn exec_result(
         &self,
         machine_name: &MachineName,
@@ -165,10 +833,33 @@ impl ForeverVMClient {
         .await
     }
 
+    pub async fn get_machine(&self, machine_name: &MachineName) -> Result<ApiMachine> {
+        let response: GetMachineResponse = self
+            .get_request(&format!("/machine/{machine_name}"))
+            .await?;
+        Ok(response.machine)
+    }
+
     pub async fn whoami(&self) -> Result<WhoamiResponse> {
         self.get_request("/whoami").await
     }
 
+    /// Subscribes to lifecycle events for the account's machines, optionally filtered by tag.
+    ///
+    /// The server streams events as newline-delimited JSON for as long as the returned stream
+    /// is held; dropping it ends the subscription.
+    pub async fn machine_events(
+        &self,
+        filter: MachineEventsRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<MachineEvent>> + Send>>> {
+        let url = self.api_base.join("/v1/machine/events")?;
+        let mut request = self.new_request(RequestKind::Stream, Method::POST, url)?;
+        request.body = Some(serde_json::to_vec(&filter)?);
+
+        let response = self.send(request).await?;
+        Ok(response.ndjson())
+    }
+
     /// Returns a stream of `MessageFromServer` values from the execution result endpoint.
     ///
     /// This method uses HTTP streaming to receive newline-delimited JSON responses
@@ -182,43 +873,82 @@ impl ForeverVMClient {
             "/v1/machine/{machine_name}/exec/{instruction}/stream-result"
         ))?;
 
-        let request = self
-            .client
-            .request(Method::GET, url)
-            .headers(ForeverVMClient::headers())
-            .bearer_auth(self.token.to_string())
-            .build()?;
+        let request = self.new_request(RequestKind::Stream, Method::GET, url)?;
+        let response = self.send(request).await?;
 
-        let response = self.client.execute(request).await?;
+        Ok(response.ndjson())
+    }
+}
 
-        if !response.status().is_success() {
-            return Err(parse_error(response).await?);
+/// Calls `fetch` with exponential backoff until `condition` holds for its result, failing
+/// with [`ClientError::Timeout`] after `timeout`.
+async fn poll_until<T, F: Future<Output = Result<T>>>(
+    timeout: Duration,
+    fetch: impl Fn() -> F,
+    condition: impl Fn(&T) -> bool,
+) -> Result<T> {
+    let poll = async {
+        let mut interval = MIN_POLL_INTERVAL;
+        loop {
+            let value = fetch().await?;
+            if condition(&value) {
+                return Ok(value);
+            }
+            tokio::time::sleep(interval).await;
+            interval = (interval * 2).min(MAX_POLL_INTERVAL);
         }
+    };
+
+    tokio::time::timeout(timeout, poll)
+        .await
+        .map_err(|_| ClientError::Timeout(timeout))?
+}
 
-        let stream = async_stream::stream! {
-            let mut bytes_stream = response.bytes_stream();
-            let mut buffer = String::new();
-            while let Some(bytes) = bytes_stream.next().await {
-                let mut value = String::from_utf8_lossy(&bytes?).to_string();
-
-                'chunk: loop {
-                    if let Some((first, rest)) = value.split_once('\n') {
-                        let json = &format!("{buffer}{first}");
-                        yield match serde_json::from_str::<MessageFromServer>(json) {
-                            Ok(message) => Ok(message),
-                            Err(err) => Err(ClientError::from(err)),
-                        };
-
-                        value = String::from(rest);
-                        buffer = String::new();
-                    } else {
-                        buffer += &value;
-                        break 'chunk;
-                    }
+/// The sequence number the server assigned to an instruction, which it omits if the
+/// instruction was interrupted before being queued.
+fn instruction_seq(response: &ApiExecResponse) -> Result<InstructionSeq> {
+    match response.instruction_seq {
+        Some(seq) => Ok(seq),
+        None if response.interrupted => Err(ClientError::InstructionInterrupted),
+        None => Err(ClientError::MissingInstructionSeq),
+    }
+}
+
+/// Streams the items of a cursor-paginated listing. `fetch_page` is given the cursor of the
+/// page to fetch, starting with `cursor`, and returns that page's items and the cursor of
+/// the next one. The stream ends after an error, or after a page that is empty or has no
+/// next cursor.
+fn paginate<'a, T, F, Fut>(
+    cursor: Option<String>,
+    fetch_page: F,
+) -> Pin<Box<dyn Stream<Item = Result<T>> + Send + 'a>>
+where
+    T: Send + 'a,
+    F: Fn(Option<String>) -> Fut + Send + 'a,
+    Fut: Future<Output = Result<(Vec<T>, Option<String>)>> + Send + 'a,
+{
+    let stream = async_stream::stream! {
+        let mut cursor = cursor;
+        loop {
+            let (items, next_cursor) = match fetch_page(cursor.take()).await {
+                Ok(page) => page,
+                Err(err) => {
+                    yield Err(err);
+                    break;
                 }
+            };
+
+            let empty = items.is_empty();
+            for item in items {
+                yield Ok(item);
             }
-        };
 
-        Ok(Box::pin(stream))
-    }
+            match next_cursor {
+                Some(next_cursor) if !empty => cursor = Some(next_cursor),
+                _ => break,
+            }
+        }
+    };
+
+    Box::pin(stream)
 }
@@ -1,7 +1,9 @@
 use super::{
-    typed_socket::{websocket_connect, WebSocketRecv, WebSocketSend},
+    exec::ExecOptions,
+    proxy::ProxySetting,
+    typed_socket::{websocket_connect, SocketOptions, WebSocketRecv, WebSocketSend},
     util::authorized_request,
-    ClientError,
+    ClientError, ForeverVMClient,
 };
 use crate::api::{
     api_types::{ExecResult, Instruction},
@@ -10,6 +12,7 @@ use crate::api::{
     token::ApiToken,
 };
 use std::{
+    fmt::Debug,
     ops::{Deref, DerefMut},
     sync::{atomic::AtomicU32, Arc, Mutex},
 };
@@ -17,6 +20,7 @@ use tokio::{
     sync::{broadcast, oneshot},
     task::JoinHandle,
 };
+use tungstenite::http::Request;
 
 pub const DEFAULT_INSTRUCTION_TIMEOUT_SECONDS: i32 = 15;
 
@@ -32,12 +36,16 @@ impl RequestSeqGenerator {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub enum ReplConnectionState {
+    #[default]
     Idle,
     WaitingForInstructionSeq {
         request_id: RequestSeq,
         send_result_handle: oneshot::Sender<ExecResultHandle>,
+        /// Set when the caller stopped waiting before the server acknowledged the
+        /// instruction, so that it is interrupted as soon as the acknowledgement arrives.
+        cancelled: bool,
     },
     WaitingForResult {
         instruction_id: InstructionSeq,
@@ -46,9 +54,19 @@ pub enum ReplConnectionState {
     },
 }
 
-impl Default for ReplConnectionState {
-    fn default() -> Self {
-        Self::Idle
+/// Interrupts the connection's machine over HTTP. The REPL protocol has no message for
+/// stopping an instruction, so this uses the exec request's `interrupt` flag instead.
+#[derive(Clone)]
+struct Interrupter {
+    client: ForeverVMClient,
+    machine_name: MachineName,
+}
+
+impl Interrupter {
+    async fn interrupt(&self, instruction: InstructionSeq) {
+        self.client
+            .interrupt_after_stop(&self.machine_name, Some(instruction))
+            .await;
     }
 }
 
@@ -59,11 +77,17 @@ pub struct ReplConnection {
 
     receiver_handle: Option<JoinHandle<()>>,
     state: Arc<Mutex<ReplConnectionState>>,
+    /// `None` for connections that weren't opened through a [`ForeverVMClient`], which
+    /// can stop waiting for an instruction but can't interrupt it.
+    interrupter: Option<Interrupter>,
+    #[cfg(feature = "validate-syntax")]
+    validate_syntax: bool,
 }
 
 fn handle_message(
     message: MessageFromServer,
     state: Arc<Mutex<ReplConnectionState>>,
+    interrupter: Option<&Interrupter>,
 ) -> Result<(), ClientError> {
     let msg = message;
     match msg {
@@ -75,6 +99,7 @@ fn handle_message(
                 ReplConnectionState::WaitingForInstructionSeq {
                     request_id: expected_request_seq,
                     send_result_handle: receiver_sender,
+                    cancelled,
                 } => {
                     if request_id != expected_request_seq {
                         tracing::warn!(
@@ -82,6 +107,11 @@ fn handle_message(
                             ?expected_request_seq,
                             "Unexpected request seq"
                         );
+                        *state = ReplConnectionState::WaitingForInstructionSeq {
+                            request_id: expected_request_seq,
+                            send_result_handle: receiver_sender,
+                            cancelled,
+                        };
                         return Ok(());
                     }
 
@@ -94,9 +124,21 @@ fn handle_message(
                         result_sender,
                     };
 
+                    // The instruction's output and result are still received, and dropped, so
+                    // that the connection is idle again once it has stopped.
+                    if cancelled {
+                        if let Some(interrupter) = interrupter.cloned() {
+                            tokio::spawn(async move { interrupter.interrupt(seq).await });
+                        }
+                        return Ok(());
+                    }
+
                     let _ = receiver_sender.send(ExecResultHandle {
+                        instruction_seq: seq,
                         result: result_receiver,
                         receiver: output_receiver,
+                        options: ExecOptions::default(),
+                        interrupter: None,
                     });
                 }
                 state => {
@@ -111,8 +153,8 @@ fn handle_message(
             match old_state {
                 ReplConnectionState::WaitingForResult {
                     instruction_id: instruction_seq,
+                    output_sender,
                     result_sender,
-                    ..
                 } => {
                     if result.instruction_id != instruction_seq {
                         tracing::warn!(
@@ -120,6 +162,11 @@ fn handle_message(
                             ?result.instruction_id,
                             "Unexpected instruction seq"
                         );
+                        *state = ReplConnectionState::WaitingForResult {
+                            instruction_id: instruction_seq,
+                            output_sender,
+                            result_sender,
+                        };
                         return Ok(());
                     }
 
@@ -171,22 +218,97 @@ fn handle_message(
 async fn receive_loop(
     mut receiver: WebSocketRecv<MessageFromServer>,
     state: Arc<Mutex<ReplConnectionState>>,
+    interrupter: Option<Interrupter>,
 ) {
     while let Ok(Some(msg)) = receiver.recv().await {
-        if let Err(err) = handle_message(msg, state.clone()) {
+        if let Err(err) = handle_message(msg, state.clone(), interrupter.as_ref()) {
             tracing::error!(?err, "Failed to handle message");
         }
     }
 }
 
+/// Returns the root of the HTTP API served alongside the REPL at `url`.
+fn http_api_base(url: &reqwest::Url) -> Result<reqwest::Url, ClientError> {
+    let mut api_base = url.join("/")?;
+    let scheme = match url.scheme() {
+        "ws" => "http",
+        "wss" => "https",
+        _ => return Err(ClientError::InvalidUrl),
+    };
+    api_base
+        .set_scheme(scheme)
+        .map_err(|_| ClientError::InvalidUrl)?;
+    Ok(api_base)
+}
+
+async fn handshake(
+    req: Request<()>,
+    options: &SocketOptions,
+) -> Result<
+    (
+        WebSocketSend<MessageToServer>,
+        WebSocketRecv<MessageFromServer>,
+    ),
+    ClientError,
+> {
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+
+    websocket_connect::<MessageToServer, MessageFromServer>(req, options).await
+}
+
 impl ReplConnection {
+    /// Connects to the REPL at a `ws://` or `wss://` URL. Instructions stopped by
+    /// [`ExecOptions`] are interrupted through the HTTP API on the same host.
     pub async fn new(url: reqwest::Url, token: ApiToken) -> Result<Self, ClientError> {
-        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+        let options = SocketOptions {
+            proxy: ProxySetting::default().resolve(&url),
+            tls: None,
+        };
+        let client = ForeverVMClient::new(http_api_base(&url)?, token.clone());
+        let req = authorized_request(url, token)?;
+
+        let (sender, receiver) = handshake(req, &options).await?;
+        Self::open(sender, receiver, Some(client)).await
+    }
 
+    /// Connects to a REPL with explicit connection settings, such as a proxy or TLS configuration.
+    pub async fn connect(
+        url: reqwest::Url,
+        token: ApiToken,
+        options: &SocketOptions,
+    ) -> Result<Self, ClientError> {
         let req = authorized_request(url, token)?;
-        let (sender, mut receiver) =
-            websocket_connect::<MessageToServer, MessageFromServer>(req).await?;
+        Self::connect_request(req, options).await
+    }
 
+    /// Connects to a REPL using a prepared WebSocket handshake request.
+    pub async fn connect_request(
+        req: Request<()>,
+        options: &SocketOptions,
+    ) -> Result<Self, ClientError> {
+        let (sender, receiver) = handshake(req, options).await?;
+        Self::from_socket(sender, receiver).await
+    }
+
+    /// Starts a REPL session over an already-connected socket, e.g. one opened by a
+    /// custom [`SocketTransport`](super::transport::SocketTransport).
+    ///
+    /// Like [`ReplConnection::connect`] and [`ReplConnection::connect_request`], this has no
+    /// HTTP client to interrupt instructions with, so [`ExecOptions`] only stop the wait.
+    /// Use [`ForeverVMClient::repl`] for a connection that also interrupts them.
+    pub async fn from_socket(
+        sender: WebSocketSend<MessageToServer>,
+        receiver: WebSocketRecv<MessageFromServer>,
+    ) -> Result<Self, ClientError> {
+        Self::open(sender, receiver, None).await
+    }
+
+    /// Starts a REPL session that interrupts stopped instructions through `client`.
+    pub(crate) async fn open(
+        sender: WebSocketSend<MessageToServer>,
+        mut receiver: WebSocketRecv<MessageFromServer>,
+        client: Option<ForeverVMClient>,
+    ) -> Result<Self, ClientError> {
         let state: Arc<Mutex<ReplConnectionState>> = Arc::default();
 
         let machine_name = match receiver.recv().await? {
@@ -198,7 +320,12 @@ impl ReplConnection {
             }
         };
 
-        let receiver_handle = tokio::spawn(receive_loop(receiver, state.clone()));
+        let interrupter = client.map(|client| Interrupter {
+            client,
+            machine_name: machine_name.clone(),
+        });
+        let receiver_handle =
+            tokio::spawn(receive_loop(receiver, state.clone(), interrupter.clone()));
 
         Ok(Self {
             machine_name,
@@ -206,9 +333,20 @@ impl ReplConnection {
             sender,
             receiver_handle: Some(receiver_handle),
             state,
+            interrupter,
+            #[cfg(feature = "validate-syntax")]
+            validate_syntax: false,
         })
     }
 
+    /// Checks code for Python syntax errors locally before sending it. Invalid code fails
+    /// with [`ClientError::SyntaxError`] without reaching the machine.
+    #[cfg(feature = "validate-syntax")]
+    pub fn validate_syntax(mut self, enabled: bool) -> Self {
+        self.validate_syntax = enabled;
+        self
+    }
+
     pub async fn exec(&mut self, code: &str) -> Result<ExecResultHandle, ClientError> {
         let instruction = Instruction {
             code: code.to_string(),
@@ -221,13 +359,30 @@ impl ReplConnection {
         &mut self,
         instruction: Instruction,
     ) -> Result<ExecResultHandle, ClientError> {
+        self.exec_instruction_with_options(instruction, &ExecOptions::default())
+            .await
+    }
+
+    /// Like [`ReplConnection::exec_instruction`], but stops waiting when `options`' deadline
+    /// passes or its token is cancelled. This applies both here and to the returned handle.
+    /// Stopping also interrupts the instruction, as soon as the server has acknowledged it,
+    /// if the connection was opened with [`ForeverVMClient::repl`].
+    pub async fn exec_instruction_with_options(
+        &mut self,
+        instruction: Instruction,
+        options: &ExecOptions,
+    ) -> Result<ExecResultHandle, ClientError> {
+        options.check()?;
+
+        #[cfg(feature = "validate-syntax")]
+        if self.validate_syntax {
+            super::syntax::validate_syntax(&instruction.code)?;
+        }
+
         let request_id = self.request_seq_generator.next();
-        let message = MessageToServer::Exec {
-            instruction,
-            request_id,
-        };
-        self.sender.send(&message).await?;
 
+        // Update the state before sending, so that a fast reply can't arrive while the
+        // connection still looks idle.
         let (send_result_handle, receive_result_handle) = oneshot::channel::<ExecResultHandle>();
         {
             let mut state = self.state.lock().expect("State lock poisoned");
@@ -235,12 +390,66 @@ impl ReplConnection {
             *state.deref_mut() = ReplConnectionState::WaitingForInstructionSeq {
                 request_id,
                 send_result_handle,
+                cancelled: false,
             };
         }
 
-        receive_result_handle
-            .await
-            .map_err(|_| ClientError::InstructionInterrupted)
+        let message = MessageToServer::Exec {
+            instruction,
+            request_id,
+        };
+        self.sender.send(&message).await?;
+
+        let mut receive_result_handle = receive_result_handle;
+        let received = options
+            .run(async {
+                (&mut receive_result_handle)
+                    .await
+                    .map_err(|_| ClientError::InstructionInterrupted)
+            })
+            .await;
+
+        let mut handle = match received {
+            Ok(handle) => handle,
+            Err(err) if err.is_stopped() => {
+                self.cancel_pending(request_id, receive_result_handle).await;
+                return Err(err);
+            }
+            Err(err) => return Err(err),
+        };
+        handle.options = options.clone();
+        handle.interrupter = self.interrupter.clone();
+        Ok(handle)
+    }
+
+    /// Makes sure an instruction the caller stopped waiting for gets interrupted. If the
+    /// server hasn't acknowledged it yet, the state records the cancellation so that the
+    /// receive loop interrupts it on arrival; otherwise its handle is already waiting in
+    /// `receive_result_handle`.
+    async fn cancel_pending(
+        &mut self,
+        request_id: RequestSeq,
+        mut receive_result_handle: oneshot::Receiver<ExecResultHandle>,
+    ) {
+        {
+            let mut state = self.state.lock().expect("State lock poisoned");
+            if let ReplConnectionState::WaitingForInstructionSeq {
+                request_id: pending_request_id,
+                cancelled,
+                ..
+            } = state.deref_mut()
+            {
+                if *pending_request_id == request_id {
+                    *cancelled = true;
+                    return;
+                }
+            }
+        }
+
+        if let Ok(mut handle) = receive_result_handle.try_recv() {
+            handle.interrupter = self.interrupter.clone();
+            handle.interrupt().await;
+        }
     }
 }
 
@@ -252,20 +461,74 @@ impl Drop for ReplConnection {
     }
 }
 
-#[derive(Debug)]
 pub struct ExecResultHandle {
+    instruction_seq: InstructionSeq,
     result: oneshot::Receiver<ExecResult>,
     receiver: broadcast::Receiver<StandardOutput>,
+    options: ExecOptions,
+    /// Used to interrupt the instruction when `options` stops it. Taken once the interrupt
+    /// has been sent.
+    interrupter: Option<Interrupter>,
+}
+
+impl Debug for ExecResultHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExecResultHandle")
+            .field("instruction_seq", &self.instruction_seq)
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
 }
 
 impl ExecResultHandle {
-    pub async fn next(&mut self) -> Option<StandardOutput> {
-        self.receiver.recv().await.ok()
+    pub fn instruction_seq(&self) -> InstructionSeq {
+        self.instruction_seq
     }
 
-    pub async fn result(self) -> Result<ExecResult, ClientError> {
-        self.result
-            .await
-            .map_err(|_| ClientError::InstructionInterrupted)
+    /// Waits for the next chunk of output. Returns `None` once the instruction has finished,
+    /// or an error if it was stopped by the handle's [`ExecOptions`].
+    pub async fn next(&mut self) -> Option<Result<StandardOutput, ClientError>> {
+        let Self {
+            receiver, options, ..
+        } = self;
+        let received = options.run(async { Ok(receiver.recv().await.ok()) }).await;
+
+        match received {
+            Ok(chunk) => chunk.map(Ok),
+            Err(err) => {
+                if err.is_stopped() {
+                    self.interrupt().await;
+                }
+                Some(Err(err))
+            }
+        }
+    }
+
+    pub async fn result(mut self) -> Result<ExecResult, ClientError> {
+        let Self {
+            result, options, ..
+        } = &mut self;
+        let outcome = options
+            .run(async {
+                result
+                    .await
+                    .map_err(|_| ClientError::InstructionInterrupted)
+            })
+            .await;
+
+        if let Err(err) = &outcome {
+            if err.is_stopped() {
+                self.interrupt().await;
+            }
+        }
+        outcome
+    }
+
+    /// Interrupts the instruction's machine unless the instruction has finished, at most
+    /// once per handle.
+    async fn interrupt(&mut self) {
+        if let Some(interrupter) = self.interrupter.take() {
+            interrupter.interrupt(self.instruction_seq).await;
+        }
     }
 }
@@ -0,0 +1,80 @@
+//! Local checking of Python syntax, so that code that can't run is rejected before it
+//! reaches a machine.
+
+use std::fmt::Display;
+
+/// A syntax error found in code before it was sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub message: String,
+    /// The 1-based line of the error.
+    pub line: usize,
+    /// The 1-based column of the error, in characters.
+    pub column: usize,
+    /// The offending line, followed by a line with a caret under the error.
+    pub snippet: String,
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} (line {}, column {})\n{}",
+            self.message, self.line, self.column, self.snippet
+        )
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+/// Parses `code` as a Python module without running it.
+///
+/// The parser follows recent CPython grammar, but it isn't CPython: in rare cases it may
+/// reject code that the machine's interpreter would accept, which is why validation is
+/// opt-in.
+#[cfg(feature = "validate-syntax")]
+pub fn validate_syntax(code: &str) -> Result<(), SyntaxError> {
+    use rustpython_parser::{parse, Mode};
+
+    match parse(code, Mode::Module, "<input>") {
+        Ok(_) => Ok(()),
+        Err(err) => Err(SyntaxError::at(
+            code,
+            err.offset.into(),
+            err.error.to_string(),
+        )),
+    }
+}
+
+#[cfg(feature = "validate-syntax")]
+impl SyntaxError {
+    /// Builds an error for the byte `offset` into `code`.
+    fn at(code: &str, offset: usize, message: String) -> Self {
+        // Errors at the end of the input, such as an unclosed bracket, point just past the
+        // last code rather than at a trailing blank line.
+        let mut offset = offset.min(code.trim_end().len());
+        while !code.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let line_start = code[..offset].rfind('\n').map_or(0, |index| index + 1);
+        let line_end = code[offset..]
+            .find('\n')
+            .map_or(code.len(), |index| offset + index);
+        let source = code[line_start..line_end].trim_end_matches('\r');
+        let before = &code[line_start..offset];
+
+        // Keep tabs in the caret line, so that the caret lines up however they're rendered.
+        let padding: String = before
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        SyntaxError {
+            message,
+            line: code[..line_start].matches('\n').count() + 1,
+            column: before.chars().count() + 1,
+            snippet: format!("{source}\n{padding}^"),
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! TLS settings shared by the REST client and WebSocket connections.
+
+use super::ClientError;
+use rustls::{
+    client::{
+        danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        WebPkiServerVerifier,
+    },
+    crypto::aws_lc_rs,
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, ServerName, UnixTime},
+    ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+/// TLS configuration for connections to the foreverVM API.
+///
+/// By default, server certificates are verified against the Mozilla root store
+/// bundled with `webpki-roots`. Additional roots can be trusted (e.g. an internal CA),
+/// a client certificate can be presented for mutual TLS, and the server certificate
+/// can be pinned by its SHA-256 fingerprint.
+///
+/// The same configuration is applied to HTTP requests and REPL WebSocket connections.
+#[derive(Clone)]
+pub struct TlsConfig {
+    root_certificates: Vec<CertificateDer<'static>>,
+    built_in_roots: bool,
+    client_identity: Option<Arc<ClientIdentity>>,
+    pinned_sha256: Vec<[u8; 32]>,
+}
+
+struct ClientIdentity {
+    cert_chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            root_certificates: Vec::new(),
+            built_in_roots: true,
+            client_identity: None,
+            pinned_sha256: Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsConfig")
+            .field("root_certificates", &self.root_certificates.len())
+            .field("built_in_roots", &self.built_in_roots)
+            .field("client_identity", &self.client_identity.is_some())
+            .field("pinned_certificates", &self.pinned_sha256.len())
+            .finish()
+    }
+}
+
+impl TlsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trusts every certificate in a PEM bundle as a root, in addition to the built-in roots.
+    pub fn add_root_certificates_pem(mut self, pem: &[u8]) -> Result<Self, ClientError> {
+        let certs = CertificateDer::pem_slice_iter(pem)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| ClientError::TlsConfigError(format!("Invalid PEM: {err}")))?;
+        if certs.is_empty() {
+            return Err(ClientError::TlsConfigError(
+                "No certificates found in PEM".to_string(),
+            ));
+        }
+
+        self.root_certificates.extend(certs);
+        Ok(self)
+    }
+
+    /// Trusts a DER-encoded certificate as a root, in addition to the built-in roots.
+    pub fn add_root_certificate_der(mut self, der: Vec<u8>) -> Self {
+        self.root_certificates.push(CertificateDer::from(der));
+        self
+    }
+
+    /// Only trust roots added with `add_root_certificate*`, not the bundled Mozilla roots.
+    pub fn disable_built_in_roots(mut self) -> Self {
+        self.built_in_roots = false;
+        self
+    }
+
+    /// Presents a client certificate for mutual TLS. `cert_chain_pem` holds the leaf
+    /// certificate followed by any intermediates; `key_pem` holds its private key
+    /// (PKCS#8, PKCS#1 or SEC1).
+    pub fn client_identity_pem(
+        mut self,
+        cert_chain_pem: &[u8],
+        key_pem: &[u8],
+    ) -> Result<Self, ClientError> {
+        let cert_chain = CertificateDer::pem_slice_iter(cert_chain_pem)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| ClientError::TlsConfigError(format!("Invalid PEM: {err}")))?;
+        if cert_chain.is_empty() {
+            return Err(ClientError::TlsConfigError(
+                "No certificates found in client certificate PEM".to_string(),
+            ));
+        }
+
+        let key = PrivateKeyDer::from_pem_slice(key_pem)
+            .map_err(|err| ClientError::TlsConfigError(format!("Invalid private key: {err}")))?;
+
+        self.client_identity = Some(Arc::new(ClientIdentity { cert_chain, key }));
+        Ok(self)
+    }
+
+    /// Pins the server certificate. The connection is only accepted if the server's leaf
+    /// certificate has one of the pinned SHA-256 fingerprints (of its DER encoding), in
+    /// addition to passing normal verification.
+    pub fn pin_certificate_sha256(mut self, fingerprint: [u8; 32]) -> Self {
+        self.pinned_sha256.push(fingerprint);
+        self
+    }
+
+    /// Like [`TlsConfig::pin_certificate_sha256`], but takes the fingerprint as a hex string.
+    /// Colons are ignored, so the output of `openssl x509 -fingerprint -sha256` is accepted.
+    pub fn pin_certificate_sha256_hex(self, fingerprint: &str) -> Result<Self, ClientError> {
+        let hex: String = fingerprint.chars().filter(|c| *c != ':').collect();
+        let invalid = || ClientError::TlsConfigError(format!("Invalid fingerprint: {fingerprint}"));
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+        }
+
+        Ok(self.pin_certificate_sha256(bytes))
+    }
+
+    /// Builds the `rustls` client configuration.
+    pub fn build(&self) -> Result<Arc<ClientConfig>, ClientError> {
+        let provider = Arc::new(aws_lc_rs::default_provider());
+
+        let mut roots = RootCertStore::empty();
+        if self.built_in_roots {
+            roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        }
+        for cert in &self.root_certificates {
+            roots
+                .add(cert.clone())
+                .map_err(|err| ClientError::TlsConfigError(err.to_string()))?;
+        }
+
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|err| ClientError::TlsConfigError(err.to_string()))?;
+
+        let builder = if self.pinned_sha256.is_empty() {
+            builder.with_root_certificates(roots)
+        } else {
+            let inner = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .map_err(|err| ClientError::TlsConfigError(err.to_string()))?;
+
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(PinnedCertVerifier {
+                    inner,
+                    pins: self.pinned_sha256.clone(),
+                }))
+        };
+
+        let config = match &self.client_identity {
+            Some(identity) => builder
+                .with_client_auth_cert(identity.cert_chain.clone(), identity.key.clone_key())
+                .map_err(|err| ClientError::TlsConfigError(err.to_string()))?,
+            None => builder.with_no_client_auth(),
+        };
+
+        Ok(Arc::new(config))
+    }
+}
+
+/// Verifies the certificate chain as usual, then checks the leaf against a set of pins.
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    pins: Vec<[u8; 32]>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+
+        let fingerprint: [u8; 32] = Sha256::digest(end_entity.as_ref()).into();
+        if self.pins.contains(&fingerprint) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(
+                "Server certificate does not match any pinned fingerprint".to_string(),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
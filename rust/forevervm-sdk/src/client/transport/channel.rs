@@ -0,0 +1,148 @@
+//! In-memory transports for testing code that uses the SDK, without a network server.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! use forevervm_sdk::{
+//!     api::protocol::{MessageFromServer, MessageToServer},
+//!     client::{transport::channel::ChannelSocketTransport, ForeverVMClient},
+//! };
+//!
+//! let (transport, mut listener) = ChannelSocketTransport::new();
+//! let client = ForeverVMClient::builder("http://localhost".parse()?, "id.token".parse()?)
+//!     .socket_transport(transport)
+//!     .build()?;
+//!
+//! let machine_name = "m".to_string().into();
+//! let (repl, connection) = tokio::join!(client.repl(&machine_name), async {
+//!     let connection = listener.accept().await.expect("no connection");
+//!     connection.send(&MessageFromServer::Connected {
+//!         machine_name: "m".to_string().into(),
+//!     })?;
+//!     Ok::<_, forevervm_sdk::client::error::ClientError>(connection)
+//! });
+//! let (mut repl, mut connection) = (repl?, connection?);
+//!
+//! // Drive the REPL by sending server messages on `connection`, and read what
+//! // it sent with `connection.recv::<MessageToServer>()`.
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{HttpResponse, HttpTransport, SocketSink, SocketStream, SocketTransport};
+use crate::client::{
+    error::{ClientError, Result},
+    middleware::ClientRequest,
+};
+use futures_util::{future::BoxFuture, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::mpsc;
+
+/// A [`SocketTransport`] whose connections are handed to a paired [`ChannelListener`].
+#[derive(Clone)]
+pub struct ChannelSocketTransport {
+    connections: mpsc::UnboundedSender<ChannelConnection>,
+}
+
+impl ChannelSocketTransport {
+    pub fn new() -> (Self, ChannelListener) {
+        let (connections, receiver) = mpsc::unbounded_channel();
+        (Self { connections }, ChannelListener { receiver })
+    }
+}
+
+impl SocketTransport for ChannelSocketTransport {
+    fn connect(&self, request: ClientRequest) -> BoxFuture<'_, Result<(SocketSink, SocketStream)>> {
+        Box::pin(async move {
+            let (to_server, from_client) = mpsc::unbounded_channel::<String>();
+            let (to_client, from_server) = mpsc::unbounded_channel::<String>();
+
+            self.connections
+                .send(ChannelConnection {
+                    request,
+                    to_client,
+                    from_client,
+                })
+                .map_err(|_| ClientError::Other("Channel listener was dropped".to_string()))?;
+
+            let sink = futures_util::sink::unfold(to_server, |to_server, message: String| async {
+                to_server
+                    .send(message)
+                    .map_err(|_| ClientError::Other("Channel connection closed".to_string()))?;
+                Ok::<_, ClientError>(to_server)
+            });
+
+            let stream = futures_util::stream::unfold(from_server, |mut from_server| async {
+                let message = from_server.recv().await?;
+                Some((Ok(message), from_server))
+            });
+
+            Ok((Box::pin(sink) as SocketSink, stream.boxed()))
+        })
+    }
+}
+
+/// Receives the connections opened through a [`ChannelSocketTransport`].
+pub struct ChannelListener {
+    receiver: mpsc::UnboundedReceiver<ChannelConnection>,
+}
+
+impl ChannelListener {
+    /// Waits for the next connection. Returns `None` once every transport is dropped.
+    pub async fn accept(&mut self) -> Option<ChannelConnection> {
+        self.receiver.recv().await
+    }
+}
+
+/// The server side of an in-memory connection. Dropping it closes the connection.
+pub struct ChannelConnection {
+    /// The handshake request, after middleware has been applied.
+    pub request: ClientRequest,
+    to_client: mpsc::UnboundedSender<String>,
+    from_client: mpsc::UnboundedReceiver<String>,
+}
+
+impl ChannelConnection {
+    /// Sends a JSON-encoded message to the client.
+    pub fn send<T: Serialize>(&self, message: &T) -> Result<()> {
+        self.send_text(serde_json::to_string(message)?)
+    }
+
+    /// Sends a raw text message to the client.
+    pub fn send_text(&self, text: String) -> Result<()> {
+        self.to_client
+            .send(text)
+            .map_err(|_| ClientError::Other("Client disconnected".to_string()))
+    }
+
+    /// Receives the next message from the client, or `None` if it disconnected.
+    pub async fn recv<T: DeserializeOwned>(&mut self) -> Result<Option<T>> {
+        match self.from_client.recv().await {
+            Some(text) => Ok(Some(serde_json::from_str(&text)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+type Handler = dyn Fn(ClientRequest) -> Result<HttpResponse> + Send + Sync;
+
+/// An [`HttpTransport`] that answers every request with a function.
+pub struct MockHttpTransport {
+    handler: Box<Handler>,
+}
+
+impl MockHttpTransport {
+    pub fn new(
+        handler: impl Fn(ClientRequest) -> Result<HttpResponse> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            handler: Box::new(handler),
+        }
+    }
+}
+
+impl HttpTransport for MockHttpTransport {
+    fn execute(&self, request: ClientRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        let response = (self.handler)(request);
+        Box::pin(async move { response })
+    }
+}
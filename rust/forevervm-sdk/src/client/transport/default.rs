@@ -0,0 +1,77 @@
+use super::{HttpResponse, HttpTransport, SocketSink, SocketStream, SocketTransport};
+use crate::client::{
+    error::{ClientError, Result},
+    middleware::ClientRequest,
+    proxy::ProxySetting,
+    typed_socket::{connect_tungstenite, into_text_socket, SocketOptions},
+    util::websocket_request,
+};
+use futures_util::{future::BoxFuture, StreamExt};
+use reqwest::Client;
+use std::sync::Arc;
+
+/// Sends HTTP requests with a `reqwest::Client`.
+#[derive(Clone, Debug, Default)]
+pub struct ReqwestTransport {
+    client: Client,
+}
+
+impl ReqwestTransport {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+impl HttpTransport for ReqwestTransport {
+    fn execute(&self, request: ClientRequest) -> BoxFuture<'_, Result<HttpResponse>> {
+        Box::pin(async move {
+            let mut builder = self
+                .client
+                .request(request.method, request.url)
+                .headers(request.headers);
+            if let Some(body) = request.body {
+                builder = builder
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .body(body);
+            }
+
+            let response = builder.send().await?;
+            Ok(HttpResponse {
+                status: response.status(),
+                headers: response.headers().clone(),
+                body: response
+                    .bytes_stream()
+                    .map(|chunk| chunk.map_err(ClientError::from))
+                    .boxed(),
+            })
+        })
+    }
+}
+
+/// Opens WebSocket connections with `tokio-tungstenite`, optionally through an HTTP proxy
+/// and with a custom TLS configuration.
+#[derive(Clone, Debug, Default)]
+pub struct TungsteniteTransport {
+    proxy: ProxySetting,
+    tls: Option<Arc<rustls::ClientConfig>>,
+}
+
+impl TungsteniteTransport {
+    pub fn new(proxy: ProxySetting, tls: Option<Arc<rustls::ClientConfig>>) -> Self {
+        Self { proxy, tls }
+    }
+}
+
+impl SocketTransport for TungsteniteTransport {
+    fn connect(&self, request: ClientRequest) -> BoxFuture<'_, Result<(SocketSink, SocketStream)>> {
+        Box::pin(async move {
+            let options = SocketOptions {
+                proxy: self.proxy.resolve(&request.url),
+                tls: self.tls.clone(),
+            };
+            let req = websocket_request(request.url, request.headers)?;
+            let socket = connect_tungstenite(req, &options).await?;
+            Ok(into_text_socket(socket))
+        })
+    }
+}
@@ -0,0 +1,121 @@
+//! Pluggable transports used by [`ForeverVMClient`](super::ForeverVMClient) and
+//! [`ReplConnection`](super::repl::ReplConnection).
+//!
+//! By default, HTTP requests are sent with `reqwest` ([`ReqwestTransport`]) and REPL
+//! connections use `tokio-tungstenite` ([`TungsteniteTransport`]). The [`channel`] module
+//! provides in-memory transports for driving the SDK in tests without a server.
+
+use super::{
+    error::{ClientError, Result},
+    middleware::ClientRequest,
+};
+use bytes::Bytes;
+use futures_util::{future::BoxFuture, stream::BoxStream, Sink, StreamExt};
+use reqwest::{header::HeaderMap, StatusCode};
+use serde::de::DeserializeOwned;
+use std::pin::Pin;
+
+pub mod channel;
+mod default;
+
+pub use default::{ReqwestTransport, TungsteniteTransport};
+
+/// A response from an [`HttpTransport`]. The body is streamed, so that newline-delimited
+/// JSON endpoints can be consumed incrementally.
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: BoxStream<'static, Result<Bytes>>,
+}
+
+impl HttpResponse {
+    /// A response with a body that is already fully available.
+    pub fn from_bytes(status: StatusCode, body: impl Into<Bytes>) -> Self {
+        let body: Bytes = body.into();
+        Self {
+            status,
+            headers: HeaderMap::new(),
+            body: futures_util::stream::once(async move { Ok(body) }).boxed(),
+        }
+    }
+
+    /// Reads the whole body.
+    pub async fn bytes(mut self) -> Result<Vec<u8>> {
+        let mut body = Vec::new();
+        while let Some(chunk) = self.body.next().await {
+            body.extend_from_slice(&chunk?);
+        }
+        Ok(body)
+    }
+
+    pub async fn text(self) -> Result<String> {
+        Ok(String::from_utf8_lossy(&self.bytes().await?).into_owned())
+    }
+
+    pub async fn json<T: DeserializeOwned>(self) -> Result<T> {
+        Ok(serde_json::from_slice(&self.bytes().await?)?)
+    }
+
+    /// Parses the body as newline-delimited JSON, yielding each value as soon as its line
+    /// is complete. Blank lines are skipped. The stream ends after the first body error.
+    pub fn ndjson<T: DeserializeOwned + Send + 'static>(self) -> BoxStream<'static, Result<T>> {
+        let stream = async_stream::stream! {
+            let mut body = self.body;
+            let mut buffer = Vec::new();
+            while let Some(chunk) = body.next().await {
+                match chunk {
+                    Ok(chunk) => buffer.extend_from_slice(&chunk),
+                    Err(err) => {
+                        yield Err(err);
+                        return;
+                    }
+                }
+
+                // Lines are split on bytes rather than decoded chunks, so that a multi-byte
+                // character split across chunks is reassembled intact.
+                while let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=end).collect();
+                    if let Some(value) = parse_ndjson_line(&line) {
+                        yield value;
+                    }
+                }
+            }
+
+            if let Some(value) = parse_ndjson_line(&buffer) {
+                yield value;
+            }
+        };
+
+        stream.boxed()
+    }
+}
+
+fn parse_ndjson_line<T: DeserializeOwned>(line: &[u8]) -> Option<Result<T>> {
+    let line = line.trim_ascii();
+    if line.is_empty() {
+        return None;
+    }
+
+    Some(serde_json::from_slice(line).map_err(ClientError::from))
+}
+
+/// Sends HTTP requests for the client.
+pub trait HttpTransport: Send + Sync {
+    fn execute(&self, request: ClientRequest) -> BoxFuture<'_, Result<HttpResponse>>;
+}
+
+/// The sending half of a message-oriented socket. Each item is one text message.
+pub type SocketSink = Pin<Box<dyn Sink<String, Error = ClientError> + Send>>;
+
+/// The receiving half of a message-oriented socket. Each item is one text message; the
+/// stream ends when the connection is closed.
+pub type SocketStream = BoxStream<'static, Result<String>>;
+
+/// Opens REPL connections for the client.
+pub trait SocketTransport: Send + Sync {
+    /// Performs the connection handshake described by `request` (a [`RequestKind::WebSocket`]
+    /// request) and returns the two halves of the connection.
+    ///
+    /// [`RequestKind::WebSocket`]: super::middleware::RequestKind::WebSocket
+    fn connect(&self, request: ClientRequest) -> BoxFuture<'_, Result<(SocketSink, SocketStream)>>;
+}
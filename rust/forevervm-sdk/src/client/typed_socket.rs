@@ -1,56 +1,133 @@
-use super::ClientError;
-use futures_util::{
-    stream::{SplitSink, SplitStream},
-    SinkExt, StreamExt,
+use super::{
+    proxy::ProxyConfig,
+    transport::{SocketSink, SocketStream},
+    ClientError,
 };
+use futures_util::{future, SinkExt, StreamExt};
 use serde::{de::DeserializeOwned, Serialize};
-use std::marker::PhantomData;
+use std::{marker::PhantomData, sync::Arc};
 use tokio::net::TcpStream;
-use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::{Connector, MaybeTlsStream, WebSocketStream};
 use tungstenite::{client::IntoClientRequest, Message};
 
+/// Settings applied when opening a WebSocket connection.
+#[derive(Clone, Debug, Default)]
+pub struct SocketOptions {
+    /// If set, the connection is tunneled through this proxy with HTTP `CONNECT`.
+    pub proxy: Option<ProxyConfig>,
+
+    /// TLS configuration for `wss` connections. If unset, the bundled webpki roots are used.
+    pub tls: Option<Arc<rustls::ClientConfig>>,
+}
+
+pub(crate) async fn connect_tungstenite(
+    req: impl IntoClientRequest + Unpin,
+    options: &SocketOptions,
+) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, ClientError> {
+    let req = req.into_client_request()?;
+    let connector = options.tls.clone().map(Connector::Rustls);
+
+    let socket = match &options.proxy {
+        Some(proxy) => {
+            let uri = req.uri();
+            let host = uri.host().ok_or(ClientError::InvalidUrl)?.to_string();
+            let port = uri
+                .port_u16()
+                .unwrap_or(if uri.scheme_str() == Some("wss") {
+                    443
+                } else {
+                    80
+                });
+
+            let stream = proxy.tunnel(&host, port).await?;
+            let (socket, _) =
+                tokio_tungstenite::client_async_tls_with_config(req, stream, None, connector)
+                    .await?;
+            socket
+        }
+        None => {
+            let (socket, _) =
+                tokio_tungstenite::connect_async_tls_with_config(req, None, false, connector)
+                    .await?;
+            socket
+        }
+    };
+
+    Ok(socket)
+}
+
+/// Adapts a WebSocket into a stream of text messages. Control frames are skipped, and
+/// binary messages are decoded as UTF-8.
+pub(crate) fn into_text_socket(
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+) -> (SocketSink, SocketStream) {
+    let (socket_send, socket_recv) = socket.split();
+
+    let sink = socket_send
+        .sink_map_err(ClientError::from)
+        .with(|text: String| future::ready(Ok::<_, ClientError>(Message::Text(text.into()))));
+
+    let stream = socket_recv.filter_map(|message| {
+        future::ready(match message {
+            Ok(Message::Text(text)) => Some(Ok(text.to_string())),
+            Ok(Message::Binary(data)) => Some(
+                String::from_utf8(data.to_vec())
+                    .map_err(|_| ClientError::Other("Received non-UTF-8 message".to_string())),
+            ),
+            Ok(_) => None,
+            Err(err) => Some(Err(ClientError::from(err))),
+        })
+    });
+
+    (Box::pin(sink), stream.boxed())
+}
+
 pub async fn websocket_connect<Send: Serialize, Recv: DeserializeOwned>(
     req: impl IntoClientRequest + Unpin,
+    options: &SocketOptions,
 ) -> Result<(WebSocketSend<Send>, WebSocketRecv<Recv>), ClientError> {
-    let (socket, _) = tokio_tungstenite::connect_async(req).await?;
-    let (socket_send, socket_recv) = socket.split();
+    let socket = connect_tungstenite(req, options).await?;
+    let (sink, stream) = into_text_socket(socket);
 
-    Ok((
-        WebSocketSend {
-            socket_send,
-            _phantom: PhantomData,
-        },
-        WebSocketRecv {
-            socket_recv,
-            _phantom: PhantomData,
-        },
-    ))
+    Ok((WebSocketSend::new(sink), WebSocketRecv::new(stream)))
 }
 
 pub struct WebSocketSend<Send: Serialize> {
-    socket_send: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
+    socket_send: SocketSink,
     _phantom: PhantomData<Send>,
 }
 
 impl<Send: Serialize> WebSocketSend<Send> {
+    pub fn new(socket_send: SocketSink) -> Self {
+        Self {
+            socket_send,
+            _phantom: PhantomData,
+        }
+    }
+
     pub async fn send(&mut self, msg: &Send) -> Result<(), ClientError> {
-        self.socket_send
-            .send(Message::Text(serde_json::to_string(msg)?.into()))
-            .await?;
+        self.socket_send.send(serde_json::to_string(msg)?).await?;
         Ok(())
     }
 }
 
 pub struct WebSocketRecv<Recv: DeserializeOwned> {
-    socket_recv: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
+    socket_recv: SocketStream,
     _phantom: PhantomData<Recv>,
 }
 
 impl<Recv: DeserializeOwned> WebSocketRecv<Recv> {
+    pub fn new(socket_recv: SocketStream) -> Self {
+        Self {
+            socket_recv,
+            _phantom: PhantomData,
+        }
+    }
+
     pub async fn recv(&mut self) -> Result<Option<Recv>, ClientError> {
         let Some(msg) = self.socket_recv.next().await else {
             return Ok(None);
         };
-        Ok(serde_json::from_str(&msg?.into_text()?)?)
+        Ok(Some(serde_json::from_str(&msg?)?))
     }
 }
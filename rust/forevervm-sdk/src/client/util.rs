@@ -1,23 +1,52 @@
 use super::ClientError;
-use crate::api::token::ApiToken;
+use crate::{api::token::ApiToken, util::get_runner};
+use reqwest::header::HeaderMap;
 use tungstenite::handshake::client::generate_key;
 use tungstenite::http::{
     header::{AUTHORIZATION, CONNECTION, HOST, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_VERSION, UPGRADE},
-    Request,
+    HeaderValue, Request,
 };
+use zeroize::Zeroizing;
 
-pub fn authorized_request(url: reqwest::Url, token: ApiToken) -> Result<Request<()>, ClientError> {
+/// Headers sent with every request: SDK identification and the bearer token.
+pub fn default_headers(token: &ApiToken) -> Result<HeaderMap, ClientError> {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-forevervm-sdk", HeaderValue::from_static("rust"));
+
+    if let Some(val) = get_runner().and_then(|v| HeaderValue::from_str(&v).ok()) {
+        headers.insert("x-forevervm-runner", val);
+    }
+
+    let authorization = Zeroizing::new(format!("Bearer {}", token.expose_secret()));
+    let mut authorization = HeaderValue::from_str(&authorization)
+        .map_err(|_| ClientError::Other(String::from("Invalid characters in API token")))?;
+    authorization.set_sensitive(true);
+    headers.insert(AUTHORIZATION, authorization);
+
+    Ok(headers)
+}
+
+/// Builds a WebSocket handshake request for `url` carrying the given headers.
+pub fn websocket_request(
+    url: reqwest::Url,
+    headers: HeaderMap,
+) -> Result<Request<()>, ClientError> {
     let hostname = url.host().ok_or(ClientError::InvalidUrl)?.to_string();
 
-    Ok(Request::builder()
+    let mut request = Request::builder()
         .uri(url.to_string())
-        .header(AUTHORIZATION, format!("Bearer {token}"))
         .header(HOST, hostname)
         .header(CONNECTION, "Upgrade")
         .header(UPGRADE, "websocket")
         // ref: https://github.com/snapview/tungstenite-rs/blob/c16778797b2eeb118aa064aa5b483f90c3989627/src/client.rs#L240
         .header(SEC_WEBSOCKET_VERSION, "13")
         .header(SEC_WEBSOCKET_KEY, generate_key())
-        .header("x-forevervm-sdk", "rust")
-        .body(())?)
+        .body(())?;
+
+    request.headers_mut().extend(headers);
+    Ok(request)
+}
+
+pub fn authorized_request(url: reqwest::Url, token: ApiToken) -> Result<Request<()>, ClientError> {
+    websocket_request(url, default_headers(&token)?)
 }
@@ -1,5 +1,10 @@
 #![deny(clippy::unwrap_used)]
+// `ClientError::TungsteniteError` holds a `tungstenite::Error` by value, which is part of the
+// public API, so every `Result<_, ClientError>` is as large as that error.
+#![allow(clippy::result_large_err)]
 
 pub mod api;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod client;
 pub mod util;
@@ -203,7 +203,7 @@ async fn test_repl() {
     // Collect all output
     let mut outputs = Vec::new();
     while let Some(output) = result.next().await {
-        outputs.push(output);
+        outputs.push(output.expect("output stream failed"));
     }
 
     // Verify outputs
@@ -0,0 +1,106 @@
+#![cfg(feature = "blocking")]
+#![allow(clippy::result_large_err)]
+
+use common::builder;
+use forevervm_sdk::{
+    api::{
+        api_types::{ApiExecResultResponse, ExecResult, ExecResultType, ExecStatus},
+        id_types::InstructionSeq,
+        protocol::{MessageFromServer, MessageToServer, StandardOutput, StandardOutputStream},
+    },
+    blocking,
+    client::transport::{
+        channel::{ChannelSocketTransport, MockHttpTransport},
+        HttpResponse,
+    },
+};
+use reqwest::StatusCode;
+
+mod common;
+
+#[test]
+fn test_blocking_http() {
+    let transport = MockHttpTransport::new(|request| {
+        assert_eq!(request.url.path(), "/v1/whoami");
+        Ok(HttpResponse::from_bytes(
+            StatusCode::OK,
+            r#"{"account":"acct"}"#,
+        ))
+    });
+    let inner = builder().http_transport(transport).build().unwrap();
+    let client = blocking::ForeverVMClient::from_async(inner).unwrap();
+
+    assert_eq!(client.whoami().unwrap().account, "acct");
+}
+
+#[test]
+fn test_blocking_repl() {
+    let (transport, mut listener) = ChannelSocketTransport::new();
+    let inner = builder().socket_transport(transport).build().unwrap();
+    let client = blocking::ForeverVMClient::from_async(inner).unwrap();
+
+    // The fake server runs on its own thread and runtime, like a real remote server would.
+    let server = std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async move {
+            let mut connection = listener.accept().await.unwrap();
+            connection
+                .send(&MessageFromServer::Connected {
+                    machine_name: "m".to_string().into(),
+                })
+                .unwrap();
+
+            let request_id = match connection.recv::<MessageToServer>().await.unwrap() {
+                Some(MessageToServer::Exec { request_id, .. }) => request_id,
+                other => panic!("expected exec, got {other:?}"),
+            };
+            connection
+                .send(&MessageFromServer::ExecReceived {
+                    seq: InstructionSeq(1),
+                    request_id,
+                })
+                .unwrap();
+            for (seq, data) in ["a", "b"].into_iter().enumerate() {
+                connection
+                    .send(&MessageFromServer::Output {
+                        chunk: StandardOutput {
+                            stream: StandardOutputStream::Stdout,
+                            data: data.to_string(),
+                            seq: (seq as i64).into(),
+                        },
+                        instruction_id: InstructionSeq(1),
+                    })
+                    .unwrap();
+            }
+            connection
+                .send(&MessageFromServer::Result(ApiExecResultResponse {
+                    instruction_id: InstructionSeq(1),
+                    result: ExecResult {
+                        status: ExecStatus::Ok,
+                        result: ExecResultType::Value {
+                            value: Some("None".to_string()),
+                            data: None,
+                        },
+                        runtime_ms: 1,
+                    },
+                }))
+                .unwrap();
+
+            // Keep the connection open until the client hangs up.
+            while let Ok(Some(_)) = connection.recv::<MessageToServer>().await {}
+        });
+    });
+
+    let mut repl = client.repl(&"m".to_string().into()).unwrap();
+    assert_eq!(repl.machine_name.to_string(), "m");
+
+    let mut handle = repl.exec("print('a'); print('b')").unwrap();
+    let output: Vec<String> = (&mut handle).map(|chunk| chunk.unwrap().data).collect();
+    assert_eq!(output, vec!["a", "b"]);
+    assert_eq!(handle.result().unwrap().runtime_ms, 1);
+
+    drop(repl);
+    server.join().unwrap();
+}
@@ -0,0 +1,15 @@
+//! Helpers shared by the integration tests.
+
+use forevervm_sdk::{
+    api::token::ApiToken,
+    client::{builder::ForeverVMClientBuilder, ForeverVMClient},
+};
+use url::Url;
+
+/// A builder for a client of a fake API server, to be given a mock transport.
+pub fn builder() -> ForeverVMClientBuilder {
+    ForeverVMClient::builder(
+        Url::parse("https://api.example.com").unwrap(),
+        ApiToken::new("id.secret".to_string()).unwrap(),
+    )
+}
@@ -0,0 +1,89 @@
+use forevervm_sdk::api::api_types::{ExecResult, ExecResultType, ExecStatus};
+
+fn parse(json: &str) -> ExecResult {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn test_legacy_value() {
+    let result = parse(r#"{"value":"2","data":null,"runtime_ms":3}"#);
+    assert_eq!(result.status, ExecStatus::Ok);
+    assert_eq!(
+        result.result,
+        ExecResultType::Value {
+            value: Some("2".to_string()),
+            data: None
+        }
+    );
+    assert_eq!(result.runtime_ms, 3);
+}
+
+#[test]
+fn test_legacy_error() {
+    let result = parse(r#"{"error":"NameError: name 'x' is not defined","runtime_ms":1}"#);
+    assert_eq!(result.status, ExecStatus::PythonException);
+    assert_eq!(
+        result.result,
+        ExecResultType::Error {
+            error: "NameError: name 'x' is not defined".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_legacy_neither_is_ok() {
+    let result = parse(r#"{"runtime_ms":1}"#);
+    assert!(result.is_ok());
+    assert_eq!(
+        result.result,
+        ExecResultType::Value {
+            value: None,
+            data: None
+        }
+    );
+}
+
+#[test]
+fn test_explicit_status() {
+    let result = parse(r#"{"status":"timeout","error":"Timed out after 15s","runtime_ms":15000}"#);
+    assert_eq!(result.status, ExecStatus::Timeout);
+
+    let result = parse(r#"{"status":"out_of_memory","runtime_ms":40}"#);
+    assert_eq!(result.status, ExecStatus::OutOfMemory);
+    assert_eq!(
+        result.result,
+        ExecResultType::Error {
+            error: "out of memory".to_string()
+        }
+    );
+
+    let result = parse(r#"{"status":"some_new_status","error":"?","runtime_ms":1}"#);
+    assert_eq!(result.status, ExecStatus::Unknown);
+}
+
+#[test]
+fn test_contradictory_payloads_are_rejected() {
+    for json in [
+        r#"{"error":"boom","value":"2","runtime_ms":1}"#,
+        r#"{"status":"ok","error":"boom","runtime_ms":1}"#,
+        r#"{"status":"interrupted","value":"2","runtime_ms":1}"#,
+        r#"{"status":"timeout","data":{"x":1},"runtime_ms":1}"#,
+    ] {
+        assert!(
+            serde_json::from_str::<ExecResult>(json).is_err(),
+            "accepted {json}"
+        );
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    for json in [
+        r#"{"status":"machine_error","error":"Machine crashed","runtime_ms":7}"#,
+        r#"{"value":"'hi'","data":{"x":1},"runtime_ms":2}"#,
+    ] {
+        let result = parse(json);
+        let reparsed = parse(&serde_json::to_string(&result).unwrap());
+        assert_eq!(reparsed, result);
+    }
+}
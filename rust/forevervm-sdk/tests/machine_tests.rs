@@ -0,0 +1,835 @@
+#![allow(clippy::result_large_err)]
+
+use chrono::Utc;
+use forevervm_sdk::{
+    api::{
+        api_types::{ExecResultType, Instruction, InstructionStatus, MachineEventKind},
+        http_api::{
+            CreateMachineRequest, ListInstructionsRequest, ListMachinesRequest,
+            MachineEventsRequest, MachineSortKey, SortOrder,
+        },
+        id_types::InstructionSeq,
+    },
+    client::{
+        error::ClientError,
+        exec::BatchOptions,
+        job::JobHandle,
+        middleware::ClientRequest,
+        transport::{channel::MockHttpTransport, HttpResponse},
+        ForeverVMClient,
+    },
+};
+use futures_util::StreamExt;
+use reqwest::{Method, StatusCode};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+mod common;
+
+/// A client whose HTTP requests are answered by `respond` and recorded in the returned list.
+fn mock_client(
+    respond: impl Fn(&ClientRequest) -> (StatusCode, String) + Send + Sync + 'static,
+) -> (ForeverVMClient, Arc<Mutex<Vec<ClientRequest>>>) {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let recorded = requests.clone();
+    let transport = MockHttpTransport::new(move |request| {
+        let (status, body) = respond(&request);
+        recorded.lock().unwrap().push(request);
+        Ok(HttpResponse::from_bytes(status, body))
+    });
+
+    let client = common::builder().http_transport(transport).build().unwrap();
+
+    (client, requests)
+}
+
+fn paths(requests: &Mutex<Vec<ClientRequest>>) -> Vec<(Method, String)> {
+    requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|request| (request.method.clone(), request.url.path().to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_exec_creates_machine_when_none_given() {
+    let (client, requests) = mock_client(|request| match request.url.path() {
+        "/v1/machine/new" => (StatusCode::OK, r#"{"machine_name":"fresh"}"#.into()),
+        "/v1/machine/fresh/exec" => (StatusCode::OK, r#"{"instruction_seq":0}"#.into()),
+        path => panic!("unexpected request to {path}"),
+    });
+
+    let response = client.exec(Instruction::new("1 + 1"), None).await.unwrap();
+    assert_eq!(response.instruction_seq, Some(InstructionSeq(0)));
+    assert_eq!(response.machine.unwrap().to_string(), "fresh");
+
+    assert_eq!(
+        paths(&requests),
+        vec![
+            (Method::POST, "/v1/machine/new".to_string()),
+            (Method::POST, "/v1/machine/fresh/exec".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_exec_uses_given_machine() {
+    let (client, requests) = mock_client(|_| {
+        (
+            StatusCode::OK,
+            r#"{"instruction_seq":3,"machine":"m"}"#.into(),
+        )
+    });
+
+    let response = client
+        .exec(Instruction::new("x"), Some(&"m".to_string().into()))
+        .await
+        .unwrap();
+    assert_eq!(response.instruction_seq, Some(InstructionSeq(3)));
+    assert_eq!(
+        paths(&requests),
+        vec![(Method::POST, "/v1/machine/m/exec".to_string())]
+    );
+}
+
+const STREAM_RESULT: &str = concat!(
+    r#"{"type":"output","chunk":{"stream":"stdout","data":"a\n","seq":0},"instruction_id":4}"#,
+    "\n",
+    r#"{"type":"output","chunk":{"stream":"stderr","data":"oops\n","seq":1},"instruction_id":4}"#,
+    "\n",
+    r#"{"type":"output","chunk":{"stream":"stdout","data":"b\n","seq":2},"instruction_id":4}"#,
+    "\n",
+    r#"{"type":"result","instruction_id":4,"result":{"value":"2","runtime_ms":12}}"#,
+    "\n",
+);
+
+#[tokio::test]
+async fn test_machine_handle_exec_and_wait() {
+    let (client, requests) = mock_client(|request| match request.url.path() {
+        "/v1/machine/m/exec" => (StatusCode::OK, r#"{"instruction_seq":4}"#.into()),
+        "/v1/machine/m/exec/4/stream-result" => (StatusCode::OK, STREAM_RESULT.into()),
+        path => panic!("unexpected request to {path}"),
+    });
+
+    let machine = client.machine(&"m".to_string().into());
+    let mut seen = Vec::new();
+    let output = machine
+        .exec_and_wait_with(Instruction::new("1 + 1"), |chunk| {
+            seen.push(chunk.data.clone())
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(seen, vec!["a\n", "oops\n", "b\n"]);
+    assert_eq!(output.instruction_seq, InstructionSeq(4));
+    assert_eq!(output.stdout(), "a\nb\n");
+    assert_eq!(output.stderr(), "oops\n");
+    assert_eq!(output.runtime(), Duration::from_millis(12));
+    assert_eq!(
+        output.result.result,
+        ExecResultType::Value {
+            value: Some("2".to_string()),
+            data: None
+        }
+    );
+    assert_eq!(
+        paths(&requests),
+        vec![
+            (Method::POST, "/v1/machine/m/exec".to_string()),
+            (
+                Method::GET,
+                "/v1/machine/m/exec/4/stream-result".to_string()
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_exec_and_wait_without_seq() {
+    let (client, _) = mock_client(|_| (StatusCode::OK, r#"{"interrupted":true}"#.into()));
+    let err = client
+        .exec_and_wait(&"m".to_string().into(), Instruction::new("x"))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::InstructionInterrupted), "{err}");
+
+    let (client, _) = mock_client(|_| (StatusCode::OK, r#"{"instruction_seq":null}"#.into()));
+    let err = client
+        .exec_and_wait(&"m".to_string().into(), Instruction::new("x"))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::MissingInstructionSeq), "{err}");
+}
+
+#[tokio::test]
+async fn test_exec_and_wait_stream_ends_without_result() {
+    let (client, _) = mock_client(|request| match request.url.path() {
+        "/v1/machine/m/exec" => (StatusCode::OK, r#"{"instruction_seq":4}"#.into()),
+        _ => (
+            StatusCode::OK,
+            STREAM_RESULT.lines().next().unwrap().to_string() + "\n",
+        ),
+    });
+
+    let err = client
+        .exec_and_wait(&"m".to_string().into(), Instruction::new("x"))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::InstructionInterrupted), "{err}");
+}
+
+#[tokio::test]
+async fn test_machine_handle_info_and_tags() {
+    let (client, requests) = mock_client(|_| {
+        (
+            StatusCode::OK,
+            r#"{"machine":{"name":"m","created_at":"2025-01-01T00:00:00Z","running":true,
+                "has_pending_instruction":false,"expires_at":null,"tags":{"env":"test"}}}"#
+                .into(),
+        )
+    });
+
+    let machine = client.machine(&"m".to_string().into());
+    let info = machine.info().await.unwrap();
+    assert_eq!(info.name.to_string(), "m");
+    assert!(info.running);
+
+    let tags = machine.tags().await.unwrap();
+    assert_eq!(tags.get("env").map(String::as_str), Some("test"));
+
+    assert_eq!(
+        paths(&requests),
+        vec![
+            (Method::GET, "/v1/machine/m".to_string()),
+            (Method::GET, "/v1/machine/m".to_string()),
+        ]
+    );
+}
+
+fn machine_json(name: &str) -> String {
+    format!(
+        r#"{{"name":"{name}","created_at":"2025-01-01T00:00:00Z","running":false,
+            "has_pending_instruction":false,"expires_at":null}}"#
+    )
+}
+
+#[tokio::test]
+async fn test_machines_follows_cursor() {
+    let (client, requests) = mock_client(|request| {
+        let body: serde_json::Value =
+            serde_json::from_slice(request.body.as_ref().unwrap()).unwrap();
+        let page = match body["cursor"].as_str() {
+            None => format!(
+                r#"{{"machines":[{},{}],"next_cursor":"p2"}}"#,
+                machine_json("a"),
+                machine_json("b")
+            ),
+            Some("p2") => format!(
+                r#"{{"machines":[{}],"next_cursor":null}}"#,
+                machine_json("c")
+            ),
+            Some(cursor) => panic!("unexpected cursor {cursor}"),
+        };
+        (StatusCode::OK, page)
+    });
+
+    let query = ListMachinesRequest {
+        tag_selectors: vec!["env!=prod".parse().unwrap()],
+        running: Some(false),
+        sort_by: Some(MachineSortKey::CreatedAt),
+        sort_order: Some(SortOrder::Desc),
+        limit: Some(2),
+        ..Default::default()
+    };
+    let names: Vec<String> = client
+        .machines(query)
+        .map(|machine| machine.unwrap().name.to_string())
+        .collect()
+        .await;
+    assert_eq!(names, vec!["a", "b", "c"]);
+
+    let bodies: Vec<serde_json::Value> = requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|request| serde_json::from_slice(request.body.as_ref().unwrap()).unwrap())
+        .collect();
+    assert_eq!(
+        bodies[0],
+        serde_json::json!({
+            "tag_selectors": ["env!=prod"],
+            "running": false,
+            "sort_by": "created_at",
+            "sort_order": "desc",
+            "limit": 2,
+        })
+    );
+    assert_eq!(bodies[1]["cursor"], "p2");
+}
+
+#[tokio::test]
+async fn test_machines_stops_on_error() {
+    let (client, requests) = mock_client(|_| (StatusCode::INTERNAL_SERVER_ERROR, "boom".into()));
+
+    let results: Vec<_> = client.machines(Default::default()).collect().await;
+    assert_eq!(results.len(), 1);
+    assert!(results[0].is_err());
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_delete_machine() {
+    let (client, requests) = mock_client(|_| (StatusCode::OK, "{}".into()));
+
+    client
+        .machine(&"m".to_string().into())
+        .delete()
+        .await
+        .unwrap();
+    assert_eq!(
+        paths(&requests),
+        vec![(Method::POST, "/v1/machine/m/delete".to_string())]
+    );
+
+    let (client, _) = mock_client(|_| {
+        (
+            StatusCode::NOT_FOUND,
+            r#"{"code":"MachineNotFound","id":null}"#.into(),
+        )
+    });
+    let err = client
+        .delete_machine(&"m".to_string().into())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::ApiError(_)), "{err}");
+}
+
+#[tokio::test]
+async fn test_update_tags() {
+    let (client, requests) = mock_client(|_| {
+        (
+            StatusCode::OK,
+            r#"{"machine":{"name":"m","created_at":"2025-01-01T00:00:00Z","running":true,
+                "has_pending_instruction":false,"expires_at":null,"tags":{"owner":"b"}}}"#
+                .into(),
+        )
+    });
+
+    let machine = client
+        .update_tags(
+            &"m".to_string().into(),
+            [("owner".to_string(), "b".to_string())].into(),
+            vec!["purpose".to_string()],
+        )
+        .await
+        .unwrap();
+    assert_eq!(machine.tags.get("owner").map(String::as_str), Some("b"));
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].url.path(), "/v1/machine/m/tags");
+    let body: serde_json::Value =
+        serde_json::from_slice(requests[0].body.as_ref().unwrap()).unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({"set": {"owner": "b"}, "remove": ["purpose"]})
+    );
+}
+
+#[tokio::test]
+async fn test_update_tags_rejects_conflicting_keys() {
+    let (client, requests) = mock_client(|_| panic!("no request expected"));
+
+    let err = client
+        .update_tags(
+            &"m".to_string().into(),
+            [("owner".to_string(), "b".to_string())].into(),
+            vec!["owner".to_string()],
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::Other(_)), "{err}");
+    assert!(requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_create_machine_with_ttl() {
+    let (client, requests) = mock_client(|_| (StatusCode::OK, r#"{"machine_name":"m"}"#.into()));
+
+    client
+        .create_machine(CreateMachineRequest {
+            ttl_seconds: Some(7200),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let body: serde_json::Value =
+        serde_json::from_slice(requests.lock().unwrap()[0].body.as_ref().unwrap()).unwrap();
+    assert_eq!(body, serde_json::json!({"ttl_seconds": 7200}));
+
+    let err = client
+        .create_machine(CreateMachineRequest {
+            ttl_seconds: Some(7200),
+            expires_at: Some(Utc::now()),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::Other(_)), "{err}");
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_extend_expiry() {
+    let (client, requests) = mock_client(|_| {
+        (
+            StatusCode::OK,
+            r#"{"machine":{"name":"m","created_at":"2025-01-01T00:00:00Z","running":true,
+                "has_pending_instruction":false,"expires_at":"2025-01-03T00:00:00Z"}}"#
+                .into(),
+        )
+    });
+
+    let machine = client
+        .machine(&"m".to_string().into())
+        .extend_expiry(Duration::from_secs(24 * 60 * 60))
+        .await
+        .unwrap();
+    assert_eq!(
+        machine.expires_at.unwrap().to_rfc3339(),
+        "2025-01-03T00:00:00+00:00"
+    );
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].url.path(), "/v1/machine/m/extend");
+    let body: serde_json::Value =
+        serde_json::from_slice(requests[0].body.as_ref().unwrap()).unwrap();
+    assert_eq!(body, serde_json::json!({"extend_by_seconds": 86400}));
+}
+
+#[tokio::test]
+async fn test_resize_machine() {
+    let (client, requests) = mock_client(|_| {
+        (
+            StatusCode::OK,
+            r#"{"machine":{"name":"m","created_at":"2025-01-01T00:00:00Z","running":true,
+                "has_pending_instruction":false,"expires_at":null,"memory_mb":2048,"vcpus":2}}"#
+                .into(),
+        )
+    });
+
+    let machine = client
+        .machine(&"m".to_string().into())
+        .resize(2048)
+        .await
+        .unwrap();
+    assert_eq!(machine.memory_mb, Some(2048));
+    assert_eq!(machine.vcpus, Some(2));
+    assert_eq!(machine.disk_mb, None);
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].url.path(), "/v1/machine/m/resize");
+    let body: serde_json::Value =
+        serde_json::from_slice(requests[0].body.as_ref().unwrap()).unwrap();
+    assert_eq!(body, serde_json::json!({"memory_mb": 2048}));
+}
+
+fn machine_state(running: bool) -> String {
+    format!(
+        r#"{{"machine":{{"name":"m","created_at":"2025-01-01T00:00:00Z","running":{running},
+            "has_pending_instruction":false,"expires_at":null}}}}"#
+    )
+}
+
+#[tokio::test]
+async fn test_suspend_machine() {
+    let (client, requests) = mock_client(|_| (StatusCode::OK, machine_state(false)));
+
+    let machine = client
+        .machine(&"m".to_string().into())
+        .suspend()
+        .await
+        .unwrap();
+    assert!(!machine.running);
+    assert_eq!(
+        paths(&requests),
+        vec![(Method::POST, "/v1/machine/m/suspend".to_string())]
+    );
+}
+
+#[tokio::test]
+async fn test_resume_machine_waits_until_running() {
+    let polls = AtomicUsize::new(0);
+    let (client, requests) = mock_client(move |request| match request.url.path() {
+        "/v1/machine/m/resume" => (StatusCode::OK, machine_state(false)),
+        "/v1/machine/m" => {
+            let running = polls.fetch_add(1, Ordering::SeqCst) >= 1;
+            (StatusCode::OK, machine_state(running))
+        }
+        path => panic!("unexpected request to {path}"),
+    });
+
+    let machine = client
+        .resume_machine(&"m".to_string().into(), Duration::from_secs(10))
+        .await
+        .unwrap();
+    assert!(machine.running);
+    assert_eq!(
+        paths(&requests),
+        vec![
+            (Method::POST, "/v1/machine/m/resume".to_string()),
+            (Method::GET, "/v1/machine/m".to_string()),
+            (Method::GET, "/v1/machine/m".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_resume_machine_times_out() {
+    let (client, _) = mock_client(|_| (StatusCode::OK, machine_state(false)));
+
+    let err = client
+        .resume_machine(&"m".to_string().into(), Duration::from_millis(50))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::Timeout(_)), "{err}");
+}
+
+#[tokio::test]
+async fn test_wait_idle() {
+    let polls = AtomicUsize::new(0);
+    let (client, requests) = mock_client(move |_| {
+        let pending = polls.fetch_add(1, Ordering::SeqCst) < 2;
+        (
+            StatusCode::OK,
+            format!(
+                r#"{{"machine":{{"name":"m","created_at":"2025-01-01T00:00:00Z","running":true,
+                    "has_pending_instruction":{pending},"expires_at":null}}}}"#
+            ),
+        )
+    });
+
+    let machine = client
+        .machine(&"m".to_string().into())
+        .wait_idle(Duration::from_secs(10))
+        .await
+        .unwrap();
+    assert!(!machine.has_pending_instruction);
+    assert_eq!(requests.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_machine_events() {
+    let (client, requests) = mock_client(|_| {
+        let machine = r#"{"name":"m","created_at":"2025-01-01T00:00:00Z","running":true,
+            "has_pending_instruction":false,"expires_at":null}"#
+            .replace('\n', "");
+        let events = [
+            format!(
+                r#"{{"kind":"started","machine_name":"m","machine":{machine},"timestamp":"2025-01-01T00:00:01Z"}}"#
+            ),
+            format!(
+                r#"{{"kind":"rebooted","machine_name":"m","machine":{machine},"timestamp":"2025-01-01T00:00:02Z"}}"#
+            ),
+        ];
+        (StatusCode::OK, events.join("\n") + "\n")
+    });
+
+    let filter = MachineEventsRequest {
+        tags: [("env".to_string(), "test".to_string())].into(),
+        ..Default::default()
+    };
+    let events: Vec<_> = client
+        .machine_events(filter)
+        .await
+        .unwrap()
+        .map(|event| event.unwrap())
+        .collect()
+        .await;
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].kind, MachineEventKind::Started);
+    assert_eq!(events[0].machine_name.to_string(), "m");
+    assert!(events[0].machine.running);
+    assert_eq!(events[1].kind, MachineEventKind::Unknown);
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].method, Method::POST);
+    assert_eq!(requests[0].url.path(), "/v1/machine/events");
+    let body: serde_json::Value =
+        serde_json::from_slice(requests[0].body.as_ref().unwrap()).unwrap();
+    assert_eq!(body, serde_json::json!({"tags": {"env": "test"}}));
+}
+
+fn instruction_json(seq: i64, status: &str) -> String {
+    format!(
+        r#"{{"seq":{seq},"code":"x = {seq}","status":"{status}","created_at":"2025-01-01T00:00:00Z",
+            "started_at":null,"finished_at":null,"runtime_ms":null,"result":null}}"#
+    )
+}
+
+#[tokio::test]
+async fn test_instructions_follows_cursor() {
+    let (client, requests) = mock_client(|request| {
+        let body: serde_json::Value =
+            serde_json::from_slice(request.body.as_ref().unwrap()).unwrap();
+        let page = match body["cursor"].as_str() {
+            None => format!(
+                r#"{{"instructions":[{},{}],"next_cursor":"next"}}"#,
+                instruction_json(0, "completed"),
+                instruction_json(1, "error")
+            ),
+            Some("next") => format!(r#"{{"instructions":[{}]}}"#, instruction_json(2, "running")),
+            Some(cursor) => panic!("unexpected cursor {cursor}"),
+        };
+        (StatusCode::OK, page)
+    });
+
+    let machine_name = "m".to_string().into();
+    let query = ListInstructionsRequest {
+        include_output: true,
+        ..Default::default()
+    };
+    let instructions: Vec<_> = client
+        .instructions(&machine_name, query)
+        .map(|instruction| instruction.unwrap())
+        .collect()
+        .await;
+
+    let summary: Vec<_> = instructions
+        .iter()
+        .map(|instruction| (instruction.seq, instruction.status))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (InstructionSeq(0), InstructionStatus::Completed),
+            (InstructionSeq(1), InstructionStatus::Error),
+            (InstructionSeq(2), InstructionStatus::Running),
+        ]
+    );
+    assert_eq!(instructions[2].code, "x = 2");
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].url.path(), "/v1/machine/m/instructions");
+    let body: serde_json::Value =
+        serde_json::from_slice(requests[0].body.as_ref().unwrap()).unwrap();
+    assert_eq!(body, serde_json::json!({"include_output": true}));
+}
+
+#[tokio::test]
+async fn test_submit_job_and_wait() {
+    let polls = AtomicUsize::new(0);
+    let (client, requests) = mock_client(move |request| match request.url.path() {
+        "/v1/machine/new" => (StatusCode::OK, r#"{"machine_name":"fresh"}"#.into()),
+        "/v1/machine/fresh/exec" => (StatusCode::OK, r#"{"instruction_seq":7}"#.into()),
+        "/v1/machine/fresh/instructions/7" => {
+            let status = match polls.fetch_add(1, Ordering::SeqCst) {
+                0 => "pending",
+                1 => "running",
+                _ => "completed",
+            };
+            (
+                StatusCode::OK,
+                format!(r#"{{"instruction":{}}}"#, instruction_json(7, status)),
+            )
+        }
+        path => panic!("unexpected request to {path}"),
+    });
+
+    let job = client
+        .submit_job(Instruction::new("x = 7"), None)
+        .await
+        .unwrap();
+    assert_eq!(job.to_string(), "fresh/7");
+
+    let job: JobHandle = job.to_string().parse().unwrap();
+    let instruction = job.wait(&client, Duration::from_secs(10)).await.unwrap();
+    assert_eq!(instruction.status, InstructionStatus::Completed);
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 5);
+    assert_eq!(requests[4].method, Method::GET);
+}
+
+#[tokio::test]
+async fn test_wait_instruction_stops_at_result_with_unknown_status() {
+    let (client, requests) = mock_client(|_| {
+        let instruction = instruction_json(3, "archived").replace(
+            r#""result":null"#,
+            r#""result":{"value":"3","data":null,"runtime_ms":1}"#,
+        );
+        (
+            StatusCode::OK,
+            format!(r#"{{"instruction":{instruction}}}"#),
+        )
+    });
+
+    let instruction = client
+        .wait_instruction(
+            &"m".to_string().into(),
+            InstructionSeq(3),
+            Duration::from_secs(10),
+        )
+        .await
+        .unwrap();
+    assert_eq!(instruction.status, InstructionStatus::Unknown);
+    assert!(instruction.result.is_some());
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_job_handle_parse() {
+    let job: JobHandle = "my-machine/12".parse().unwrap();
+    assert_eq!(job.machine.to_string(), "my-machine");
+    assert_eq!(job.seq, InstructionSeq(12));
+
+    assert!("my-machine".parse::<JobHandle>().is_err());
+    assert!("/12".parse::<JobHandle>().is_err());
+    assert!("my-machine/last".parse::<JobHandle>().is_err());
+    assert!("my-machine/-1".parse::<JobHandle>().is_err());
+}
+
+/// Answers `exec` with increasing sequence numbers, and streams each instruction's code back
+/// as its output. Code starting with `raise` produces an error result.
+fn batch_client() -> (ForeverVMClient, Arc<Mutex<Vec<ClientRequest>>>) {
+    let submitted = Mutex::new(Vec::<String>::new());
+    mock_client(move |request| {
+        let path = request.url.path();
+        if path == "/v1/machine/m/exec" {
+            let body: serde_json::Value =
+                serde_json::from_slice(request.body.as_ref().unwrap()).unwrap();
+            let mut submitted = submitted.lock().unwrap();
+            submitted.push(body["instruction"]["code"].as_str().unwrap().to_string());
+            return (
+                StatusCode::OK,
+                format!(r#"{{"instruction_seq":{}}}"#, submitted.len() - 1),
+            );
+        }
+
+        let seq: usize = path
+            .strip_prefix("/v1/machine/m/exec/")
+            .and_then(|rest| rest.strip_suffix("/stream-result"))
+            .unwrap_or_else(|| panic!("unexpected request to {path}"))
+            .parse()
+            .unwrap();
+        let code = submitted.lock().unwrap()[seq].clone();
+        let result = if code.starts_with("raise") {
+            r#""error":"Exception""#
+        } else {
+            r#""value":null"#
+        };
+        let body = format!(
+            r#"{{"type":"output","chunk":{{"stream":"stdout","data":"{code}","seq":0}},"instruction_id":{seq}}}
+{{"type":"result","instruction_id":{seq},"result":{{{result},"runtime_ms":1}}}}
+"#
+        );
+        (StatusCode::OK, body)
+    })
+}
+
+#[tokio::test]
+async fn test_exec_batch_submits_up_front() {
+    let (client, requests) = batch_client();
+
+    let instructions = ["a", "raise b", "c"].map(Instruction::new).to_vec();
+    let outputs = client
+        .machine(&"m".to_string().into())
+        .exec_batch(instructions, BatchOptions::default())
+        .await
+        .unwrap();
+
+    let stdout: Vec<_> = outputs.iter().map(|output| output.stdout()).collect();
+    assert_eq!(stdout, vec!["a", "raise b", "c"]);
+    assert!(outputs[1].is_error());
+
+    let methods: Vec<_> = paths(&requests)
+        .into_iter()
+        .map(|(method, _)| method)
+        .collect();
+    assert_eq!(
+        methods,
+        vec![
+            Method::POST,
+            Method::POST,
+            Method::POST,
+            Method::GET,
+            Method::GET,
+            Method::GET
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_exec_batch_stop_on_error() {
+    let (client, requests) = batch_client();
+
+    let instructions = ["a", "raise b", "c"].map(Instruction::new).to_vec();
+    let options = BatchOptions {
+        stop_on_error: true,
+    };
+    let outputs = client
+        .exec_batch(&"m".to_string().into(), instructions, options)
+        .await
+        .unwrap();
+
+    assert_eq!(outputs.len(), 2);
+    assert!(!outputs[0].is_error());
+    assert!(outputs[1].is_error());
+    assert_eq!(requests.lock().unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_exec_batch_failure_reports_progress() {
+    let (client, _) = mock_client(|request| {
+        let path = request.url.path();
+        if path == "/v1/machine/m/exec" {
+            let body: serde_json::Value =
+                serde_json::from_slice(request.body.as_ref().unwrap()).unwrap();
+            return match body["instruction"]["code"].as_str().unwrap() {
+                "a" => (StatusCode::OK, r#"{"instruction_seq":0}"#.to_string()),
+                "b" => (StatusCode::OK, r#"{"instruction_seq":1}"#.to_string()),
+                _ => (StatusCode::INTERNAL_SERVER_ERROR, "boom".to_string()),
+            };
+        }
+        if path == "/v1/machine/m/exec/0/stream-result" {
+            return (
+                StatusCode::OK,
+                r#"{"type":"result","instruction_id":0,"result":{"value":"1","runtime_ms":1}}"#
+                    .to_string(),
+            );
+        }
+        (StatusCode::INTERNAL_SERVER_ERROR, "boom".to_string())
+    });
+    let machine = "m".to_string().into();
+
+    // Submission fails on the third instruction, after two were queued.
+    let instructions = ["a", "b", "c"].map(Instruction::new).to_vec();
+    let err = client
+        .exec_batch(&machine, instructions, BatchOptions::default())
+        .await
+        .unwrap_err();
+    let ClientError::Batch(err) = err else {
+        panic!("expected batch error, got {err}");
+    };
+    assert!(err.outputs.is_empty());
+    assert_eq!(err.pending, vec![InstructionSeq(0), InstructionSeq(1)]);
+
+    // Collecting the second instruction's output fails.
+    let instructions = ["a", "b"].map(Instruction::new).to_vec();
+    let err = client
+        .exec_batch(&machine, instructions, BatchOptions::default())
+        .await
+        .unwrap_err();
+    let ClientError::Batch(err) = err else {
+        panic!("expected batch error, got {err}");
+    };
+    assert_eq!(err.outputs.len(), 1);
+    assert_eq!(err.pending, vec![InstructionSeq(1)]);
+}
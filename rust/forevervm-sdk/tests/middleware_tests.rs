@@ -0,0 +1,177 @@
+use forevervm_sdk::{
+    api::{http_api::WhoamiResponse, protocol::MessageFromServer, token::ApiToken},
+    client::{
+        error::{ClientError, Result},
+        middleware::{ClientRequest, ClientResponse, Middleware, RequestAction, ShortCircuit},
+        ForeverVMClient,
+    },
+};
+use futures_util::{future::BoxFuture, SinkExt, StreamExt};
+use reqwest::{header::HeaderValue, StatusCode};
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use url::Url;
+
+fn token() -> ApiToken {
+    ApiToken::new("id.secret".to_string()).unwrap()
+}
+
+/// Adds a tenant header and records what it saw.
+#[derive(Default, Clone)]
+struct Recorder {
+    requests: Arc<Mutex<Vec<ClientRequest>>>,
+    statuses: Arc<Mutex<Vec<StatusCode>>>,
+}
+
+impl Middleware for Recorder {
+    fn on_request<'a>(
+        &'a self,
+        request: &'a mut ClientRequest,
+    ) -> BoxFuture<'a, Result<RequestAction>> {
+        Box::pin(async move {
+            request
+                .headers
+                .insert("x-tenant", HeaderValue::from_static("acme"));
+            self.requests.lock().unwrap().push(request.clone());
+            Ok(RequestAction::Continue)
+        })
+    }
+
+    fn on_response(&self, _request: &ClientRequest, response: &ClientResponse) {
+        self.statuses.lock().unwrap().push(response.status);
+    }
+}
+
+/// Answers every request locally.
+struct Canned(StatusCode, serde_json::Value);
+
+impl Middleware for Canned {
+    fn on_request<'a>(
+        &'a self,
+        _request: &'a mut ClientRequest,
+    ) -> BoxFuture<'a, Result<RequestAction>> {
+        Box::pin(async move { Ok(RequestAction::Respond(ShortCircuit::json(self.0, &self.1)?)) })
+    }
+}
+
+#[tokio::test]
+async fn test_middleware_can_short_circuit() {
+    let recorder = Recorder::default();
+    let after = Recorder::default();
+    let client = ForeverVMClient::builder(Url::parse("http://127.0.0.1:1").unwrap(), token())
+        .middleware(recorder.clone())
+        .middleware(Canned(
+            StatusCode::OK,
+            serde_json::json!({ "account": "acme" }),
+        ))
+        .middleware(after.clone())
+        .build()
+        .unwrap();
+
+    let whoami: WhoamiResponse = client.whoami().await.unwrap();
+    assert_eq!(whoami.account, "acme");
+
+    let requests = recorder.requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].url.path(), "/v1/whoami");
+    assert_eq!(requests[0].headers["x-forevervm-sdk"], "rust");
+    assert_eq!(requests[0].headers["authorization"], "Bearer id.secret");
+    assert!(requests[0].headers["authorization"].is_sensitive());
+    assert_eq!(*recorder.statuses.lock().unwrap(), vec![StatusCode::OK]);
+
+    // Middleware after the short circuit never sees the request.
+    assert!(after.requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_short_circuit_error_status() {
+    let client = ForeverVMClient::builder(Url::parse("http://127.0.0.1:1").unwrap(), token())
+        .middleware(Canned(
+            StatusCode::FORBIDDEN,
+            serde_json::json!({ "code": "Forbidden", "id": null }),
+        ))
+        .build()
+        .unwrap();
+
+    match client.whoami().await {
+        Err(ClientError::ApiError(err)) => assert_eq!(err.code, "Forbidden"),
+        other => panic!("expected ApiError, got {other:?}"),
+    }
+}
+
+struct Reject;
+
+impl Middleware for Reject {
+    fn on_request<'a>(
+        &'a self,
+        _request: &'a mut ClientRequest,
+    ) -> BoxFuture<'a, Result<RequestAction>> {
+        Box::pin(async { Err(ClientError::Other("rejected".to_string())) })
+    }
+}
+
+#[tokio::test]
+async fn test_middleware_error_aborts_request() {
+    let client = ForeverVMClient::builder(Url::parse("http://127.0.0.1:1").unwrap(), token())
+        .middleware(Reject)
+        .build()
+        .unwrap();
+
+    assert!(matches!(
+        client.whoami().await,
+        Err(ClientError::Other(message)) if message == "rejected"
+    ));
+}
+
+#[tokio::test]
+async fn test_middleware_applies_to_repl_handshake() {
+    let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let received_headers = Arc::new(Mutex::new(None));
+
+    let headers = received_headers.clone();
+    tokio::spawn(async move {
+        let (stream, _) = server.accept().await.unwrap();
+        #[allow(clippy::result_large_err)]
+        let callback = |request: &tungstenite::handshake::server::Request,
+                        response: tungstenite::handshake::server::Response| {
+            *headers.lock().unwrap() = Some(request.headers().clone());
+            Ok(response)
+        };
+        let mut socket = tokio_tungstenite::accept_hdr_async(stream, callback)
+            .await
+            .unwrap();
+        let connected = MessageFromServer::Connected {
+            machine_name: "machine-1".to_string().into(),
+        };
+        socket
+            .send(tungstenite::Message::Text(
+                serde_json::to_string(&connected).unwrap().into(),
+            ))
+            .await
+            .unwrap();
+        let _ = socket.next().await;
+    });
+
+    let recorder = Recorder::default();
+    let client = ForeverVMClient::builder(Url::parse(&format!("http://{addr}")).unwrap(), token())
+        .no_proxy()
+        .middleware(recorder.clone())
+        .build()
+        .unwrap();
+
+    let repl = client
+        .repl(&"machine-1".to_string().into())
+        .await
+        .expect("failed to connect");
+    assert_eq!(repl.machine_name.to_string(), "machine-1");
+
+    let headers = received_headers.lock().unwrap().clone().unwrap();
+    assert_eq!(headers["x-tenant"], "acme");
+    assert_eq!(headers["x-forevervm-sdk"], "rust");
+    assert_eq!(headers["authorization"], "Bearer id.secret");
+    assert_eq!(
+        *recorder.statuses.lock().unwrap(),
+        vec![StatusCode::SWITCHING_PROTOCOLS]
+    );
+}
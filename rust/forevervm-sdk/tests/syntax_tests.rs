@@ -0,0 +1,151 @@
+#![cfg(feature = "validate-syntax")]
+#![allow(clippy::result_large_err)]
+
+use common::builder;
+use forevervm_sdk::{
+    api::{
+        api_types::Instruction,
+        id_types::InstructionSeq,
+        protocol::{MessageFromServer, MessageToServer},
+    },
+    client::{
+        error::ClientError,
+        syntax::validate_syntax,
+        transport::{
+            channel::{ChannelSocketTransport, MockHttpTransport},
+            HttpResponse,
+        },
+    },
+};
+use reqwest::StatusCode;
+use std::sync::{Arc, Mutex};
+
+mod common;
+
+#[test]
+fn test_valid_code() {
+    for code in [
+        "",
+        "print('hi')",
+        "x = 1\nif x:\n    y = [i for i in range(x)]\n",
+        "match x:\n    case 1:\n        pass\n",
+        "async def f():\n    await g()\n",
+        "f'{x!r:>10}'",
+    ] {
+        assert_eq!(validate_syntax(code), Ok(()), "rejected {code:?}");
+    }
+}
+
+#[test]
+fn test_error_location() {
+    let err = validate_syntax("x = 1\ny = = 2\n").unwrap_err();
+    assert_eq!((err.line, err.column), (2, 5));
+    assert_eq!(err.snippet, "y = = 2\n    ^");
+
+    let err = validate_syntax("def f():\nreturn 1\n").unwrap_err();
+    assert_eq!((err.line, err.column), (2, 1));
+    assert_eq!(err.snippet, "return 1\n^");
+}
+
+#[test]
+fn test_error_at_end_of_input() {
+    // The error points past the unclosed call, not at the blank line after it.
+    let err = validate_syntax("print('a'\n\n").unwrap_err();
+    assert_eq!((err.line, err.column), (1, 10));
+    assert_eq!(err.snippet, "print('a'\n         ^");
+}
+
+#[test]
+fn test_column_counts_characters() {
+    let err = validate_syntax("s = 'é' +\n").unwrap_err();
+    assert_eq!(err.line, 1);
+    assert_eq!(
+        err.snippet,
+        format!("s = 'é' +\n{}^", " ".repeat(err.column - 1))
+    );
+
+    let err = validate_syntax("if x:\n\ty = = 1\n").unwrap_err();
+    assert_eq!((err.line, err.column), (2, 6));
+    assert_eq!(err.snippet, "\ty = = 1\n\t    ^");
+}
+
+#[tokio::test]
+async fn test_exec_instruction_rejects_invalid_code_locally() {
+    let requests = Arc::new(Mutex::new(0));
+    let recorded = requests.clone();
+    let transport = MockHttpTransport::new(move |_| {
+        *recorded.lock().unwrap() += 1;
+        Ok(HttpResponse::from_bytes(
+            StatusCode::OK,
+            r#"{"instruction_seq":1}"#,
+        ))
+    });
+    let client = builder()
+        .http_transport(transport)
+        .validate_syntax(true)
+        .build()
+        .unwrap();
+    let machine = "m".to_string().into();
+
+    let err = client
+        .exec_instruction(&machine, Instruction::new("print('a'"))
+        .await
+        .unwrap_err();
+    let ClientError::SyntaxError(err) = err else {
+        panic!("expected syntax error, got {err}");
+    };
+    assert_eq!((err.line, err.column), (1, 10));
+    assert_eq!(*requests.lock().unwrap(), 0);
+
+    client
+        .exec_instruction(&machine, Instruction::new("print('a')"))
+        .await
+        .unwrap();
+    assert_eq!(*requests.lock().unwrap(), 1);
+}
+
+#[tokio::test]
+async fn test_repl_rejects_invalid_code_locally() {
+    let (transport, mut listener) = ChannelSocketTransport::new();
+    let client = builder()
+        .socket_transport(transport)
+        .validate_syntax(true)
+        .build()
+        .unwrap();
+
+    let server = tokio::spawn(async move {
+        let mut connection = listener.accept().await.unwrap();
+        connection
+            .send(&MessageFromServer::Connected {
+                machine_name: "m".to_string().into(),
+            })
+            .unwrap();
+
+        // Only the valid instruction reaches the server.
+        match connection.recv::<MessageToServer>().await.unwrap() {
+            Some(MessageToServer::Exec {
+                instruction,
+                request_id,
+            }) => {
+                assert_eq!(instruction.code, "1 + 1");
+                connection
+                    .send(&MessageFromServer::ExecReceived {
+                        seq: InstructionSeq(1),
+                        request_id,
+                    })
+                    .unwrap();
+            }
+            other => panic!("expected exec, got {other:?}"),
+        }
+        connection
+    });
+
+    let mut repl = client.repl(&"m".to_string().into()).await.unwrap();
+    let err = repl.exec("1 +").await.unwrap_err();
+    assert!(matches!(err, ClientError::SyntaxError(_)), "{err}");
+
+    let handle = repl.exec("1 + 1").await.unwrap();
+    assert_eq!(handle.instruction_seq(), InstructionSeq(1));
+
+    drop(server.await.unwrap());
+}
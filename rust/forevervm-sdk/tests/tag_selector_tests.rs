@@ -0,0 +1,94 @@
+use forevervm_sdk::api::tag_selector::{TagSelector, TagSelectorError};
+use std::collections::HashMap;
+
+fn parse(s: &str) -> TagSelector {
+    s.parse().unwrap()
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+#[test]
+fn test_parse_selectors() {
+    assert_eq!(
+        parse("env=prod"),
+        TagSelector::Equals("env".into(), "prod".into())
+    );
+    assert_eq!(
+        parse("env == prod"),
+        TagSelector::Equals("env".into(), "prod".into())
+    );
+    assert_eq!(
+        parse("env!=prod"),
+        TagSelector::NotEquals("env".into(), "prod".into())
+    );
+    assert_eq!(
+        parse("team in (a, b)"),
+        TagSelector::In("team".into(), strings(&["a", "b"]))
+    );
+    assert_eq!(
+        parse("team notin (a,b,c)"),
+        TagSelector::NotIn("team".into(), strings(&["a", "b", "c"]))
+    );
+    assert_eq!(parse("owner exists"), TagSelector::Exists("owner".into()));
+    assert_eq!(parse("owner"), TagSelector::Exists("owner".into()));
+    assert_eq!(parse("!owner"), TagSelector::NotExists("owner".into()));
+    assert_eq!(
+        parse("url=a=b"),
+        TagSelector::Equals("url".into(), "a=b".into())
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!("".parse::<TagSelector>(), Err(TagSelectorError::Empty));
+    assert!(matches!(
+        "team in a,b".parse::<TagSelector>(),
+        Err(TagSelectorError::InvalidValueList(_))
+    ));
+    assert!(matches!(
+        "=value".parse::<TagSelector>(),
+        Err(TagSelectorError::InvalidKey(_))
+    ));
+    assert!(matches!(
+        "two words".parse::<TagSelector>(),
+        Err(TagSelectorError::InvalidKey(_))
+    ));
+}
+
+#[test]
+fn test_display_round_trips() {
+    for s in [
+        "env=prod",
+        "env!=prod",
+        "team in (a,b)",
+        "team notin (a)",
+        "owner exists",
+        "!owner",
+    ] {
+        assert_eq!(parse(s).to_string(), s);
+        assert_eq!(
+            serde_json::to_string(&parse(s)).unwrap(),
+            serde_json::to_string(s).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_matches() {
+    let tags: HashMap<String, String> = [("env", "prod"), ("team", "a")]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+    assert!(parse("env=prod").matches(&tags));
+    assert!(!parse("env!=prod").matches(&tags));
+    assert!(parse("missing!=x").matches(&tags));
+    assert!(parse("team in (a,b)").matches(&tags));
+    assert!(!parse("team notin (a,b)").matches(&tags));
+    assert!(parse("missing notin (a)").matches(&tags));
+    assert!(parse("env exists").matches(&tags));
+    assert!(parse("!missing").matches(&tags));
+    assert!(!parse("!env").matches(&tags));
+}
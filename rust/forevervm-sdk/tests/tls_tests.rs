@@ -0,0 +1,146 @@
+use forevervm_sdk::{
+    api::token::ApiToken,
+    client::{tls::TlsConfig, ForeverVMClient},
+};
+use url::Url;
+
+#[test]
+fn test_default_tls_config_builds() {
+    TlsConfig::new()
+        .build()
+        .expect("default TLS config should build");
+}
+
+#[test]
+fn test_pinned_tls_config_builds() {
+    TlsConfig::new()
+        .pin_certificate_sha256([7; 32])
+        .build()
+        .expect("pinned TLS config should build");
+}
+
+#[test]
+fn test_pin_certificate_sha256_hex() {
+    let colons = "AB:".repeat(31) + "AB";
+    assert!(TlsConfig::new().pin_certificate_sha256_hex(&colons).is_ok());
+    assert!(TlsConfig::new()
+        .pin_certificate_sha256_hex(&"ab".repeat(32))
+        .is_ok());
+
+    assert!(TlsConfig::new().pin_certificate_sha256_hex("abcd").is_err());
+    assert!(TlsConfig::new()
+        .pin_certificate_sha256_hex(&"zz".repeat(32))
+        .is_err());
+}
+
+#[test]
+fn test_invalid_pem_is_rejected() {
+    assert!(TlsConfig::new()
+        .add_root_certificates_pem(b"not a certificate")
+        .is_err());
+    assert!(TlsConfig::new()
+        .client_identity_pem(b"not a certificate", b"not a key")
+        .is_err());
+}
+
+#[test]
+fn test_client_builder_accepts_tls_config() {
+    let client = ForeverVMClient::builder(
+        Url::parse("https://api.forevervm.com").unwrap(),
+        ApiToken::new("id.token".to_string()).unwrap(),
+    )
+    .tls(TlsConfig::new().pin_certificate_sha256([0; 32]))
+    .build();
+
+    assert!(client.is_ok());
+}
+
+/// Serves one HTTPS `whoami` response with a freshly generated self-signed certificate
+/// for `localhost`. Returns the server's URL and the certificate in DER form.
+async fn tls_server() -> (Url, Vec<u8>) {
+    use rustls::{pki_types::PrivateKeyDer, ServerConfig};
+    use std::sync::Arc;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+    use tokio_rustls::TlsAcceptor;
+
+    let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let cert = certified.cert.der().clone();
+    let key = PrivateKeyDer::try_from(certified.key_pair.serialize_der()).unwrap();
+
+    let config = ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::aws_lc_rs::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .unwrap()
+    .with_no_client_auth()
+    .with_single_cert(vec![cert.clone()], key)
+    .unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        // A rejected handshake fails here, which is expected.
+        let Ok(mut stream) = acceptor.accept(stream).await else {
+            return;
+        };
+
+        let mut request = Vec::new();
+        while !request.ends_with(b"\r\n\r\n") {
+            request.push(stream.read_u8().await.unwrap());
+        }
+        let body = r#"{"account":"pinned"}"#;
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+        let _ = stream.shutdown().await;
+    });
+
+    (
+        Url::parse(&format!("https://localhost:{port}")).unwrap(),
+        cert.to_vec(),
+    )
+}
+
+fn pinned_client(api_base: Url, cert: Vec<u8>, pin: [u8; 32]) -> ForeverVMClient {
+    let tls = TlsConfig::new()
+        .disable_built_in_roots()
+        .add_root_certificate_der(cert)
+        .pin_certificate_sha256(pin);
+
+    ForeverVMClient::builder(api_base, ApiToken::new("id.token".to_string()).unwrap())
+        .no_proxy()
+        .tls(tls)
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_matching_pin_connects() {
+    use sha2::{Digest, Sha256};
+
+    let (api_base, cert) = tls_server().await;
+    let pin: [u8; 32] = Sha256::digest(&cert).into();
+
+    let client = pinned_client(api_base, cert, pin);
+    assert_eq!(client.whoami().await.unwrap().account, "pinned");
+}
+
+#[tokio::test]
+async fn test_mismatched_pin_is_rejected() {
+    let (api_base, cert) = tls_server().await;
+
+    // The certificate is trusted as a root, so only the pin can reject it.
+    let client = pinned_client(api_base, cert, [0; 32]);
+    let err = client.whoami().await.unwrap_err();
+    assert!(
+        format!("{err:?}").contains("pinned fingerprint"),
+        "unexpected error: {err:?}"
+    );
+}
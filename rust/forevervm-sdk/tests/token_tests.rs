@@ -0,0 +1,43 @@
+use forevervm_sdk::{api::token::ApiToken, client::ForeverVMClient};
+use url::Url;
+
+const TOKEN: &str = "tok_id.super-secret-value";
+
+#[test]
+fn test_token_parsing() {
+    let token = ApiToken::new(TOKEN.to_string()).unwrap();
+    assert_eq!(token.id, "tok_id");
+    assert_eq!(token.expose_secret(), TOKEN);
+
+    let parsed: ApiToken = TOKEN.parse().unwrap();
+    assert_eq!(parsed.expose_secret(), TOKEN);
+
+    assert!(ApiToken::new("no-separator".to_string()).is_err());
+}
+
+#[test]
+fn test_token_is_redacted() {
+    let token = ApiToken::new(TOKEN.to_string()).unwrap();
+
+    let debug = format!("{token:?}");
+    assert!(debug.contains("tok_id"));
+    assert!(!debug.contains("super-secret-value"));
+
+    let display = token.to_string();
+    assert!(display.starts_with("tok_id."));
+    assert!(!display.contains("super-secret-value"));
+
+    let client = ForeverVMClient::new(Url::parse("https://api.forevervm.com").unwrap(), token);
+    assert!(!format!("{client:?}").contains("super-secret-value"));
+}
+
+#[test]
+fn test_token_serde_round_trip() {
+    let token = ApiToken::new(TOKEN.to_string()).unwrap();
+
+    let json = serde_json::to_string(&token).unwrap();
+    assert_eq!(json, format!("\"{TOKEN}\""));
+
+    let token: ApiToken = serde_json::from_str(&json).unwrap();
+    assert_eq!(token.expose_secret(), TOKEN);
+}
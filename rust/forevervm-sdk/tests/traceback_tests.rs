@@ -0,0 +1,230 @@
+use forevervm_sdk::api::{
+    api_types::{ExecResult, ExecStatus},
+    traceback::{parse_traceback, ChainRelation, Frame},
+};
+
+fn frame(file: &str, line: u32, function: &str) -> Frame {
+    Frame {
+        file: file.to_string(),
+        line,
+        function: Some(function.to_string()),
+        source: None,
+        column: None,
+    }
+}
+
+#[test]
+fn test_simple_traceback() {
+    let text = concat!(
+        "Traceback (most recent call last):\n",
+        "  File \"<input>\", line 3, in <module>\n",
+        "    f(0)\n",
+        "  File \"<input>\", line 2, in f\n",
+        "    return 1 / x\n",
+        "           ~~^~~\n",
+        "ZeroDivisionError: division by zero\n",
+    );
+
+    let exception = parse_traceback(text).unwrap();
+    assert_eq!(exception.exc_type, "ZeroDivisionError");
+    assert_eq!(exception.message, "division by zero");
+    assert_eq!(
+        exception.frames,
+        vec![
+            Frame {
+                source: Some("f(0)".to_string()),
+                ..frame("<input>", 3, "<module>")
+            },
+            Frame {
+                source: Some("return 1 / x".to_string()),
+                column: Some(10),
+                ..frame("<input>", 2, "f")
+            },
+        ]
+    );
+    assert!(exception.cause_chain.is_empty());
+}
+
+#[test]
+fn test_frames_without_source_and_multiline_message() {
+    let text = concat!(
+        "Traceback (most recent call last):\n",
+        "  File \"<input>\", line 4, in <module>\n",
+        "ValueError: bad\n",
+        "value\n",
+    );
+
+    let exception = parse_traceback(text).unwrap();
+    assert_eq!(exception.frames, vec![frame("<input>", 4, "<module>")]);
+    assert_eq!(exception.message, "bad\nvalue");
+}
+
+#[test]
+fn test_exception_without_message() {
+    let exception = parse_traceback("StopIteration\n").unwrap();
+    assert_eq!(exception.exc_type, "StopIteration");
+    assert_eq!(exception.message, "");
+    assert!(exception.frames.is_empty());
+}
+
+#[test]
+fn test_recursion_marker_is_skipped() {
+    let text = concat!(
+        "Traceback (most recent call last):\n",
+        "  File \"<input>\", line 1, in f\n",
+        "    def f(): f()\n",
+        "             ^^^\n",
+        "  [Previous line repeated 996 more times]\n",
+        "RecursionError: maximum recursion depth exceeded\n",
+    );
+
+    let exception = parse_traceback(text).unwrap();
+    assert_eq!(exception.frames.len(), 1);
+    assert_eq!(exception.frames[0].source.as_deref(), Some("def f(): f()"));
+    assert_eq!(exception.frames[0].column, Some(10));
+}
+
+#[test]
+fn test_explicit_cause() {
+    let text = concat!(
+        "Traceback (most recent call last):\n",
+        "  File \"<input>\", line 2, in <module>\n",
+        "KeyError: 'k'\n",
+        "\n",
+        "The above exception was the direct cause of the following exception:\n",
+        "\n",
+        "Traceback (most recent call last):\n",
+        "  File \"<input>\", line 4, in <module>\n",
+        "ValueError: bad value\n",
+    );
+
+    let exception = parse_traceback(text).unwrap();
+    assert_eq!(exception.exc_type, "ValueError");
+    assert_eq!(exception.message, "bad value");
+    assert_eq!(exception.frames, vec![frame("<input>", 4, "<module>")]);
+
+    assert_eq!(exception.cause_chain.len(), 1);
+    let cause = &exception.cause_chain[0];
+    assert_eq!(cause.relation, ChainRelation::Cause);
+    assert_eq!(cause.exception.exc_type, "KeyError");
+    assert_eq!(cause.exception.message, "'k'");
+    assert_eq!(
+        cause.exception.frames,
+        vec![frame("<input>", 2, "<module>")]
+    );
+}
+
+#[test]
+fn test_chain_of_three() {
+    let text = concat!(
+        "Traceback (most recent call last):\n",
+        "  File \"<input>\", line 2, in <module>\n",
+        "ValueError: invalid literal for int() with base 10: 'x'\n",
+        "\n",
+        "During handling of the above exception, another exception occurred:\n",
+        "\n",
+        "Traceback (most recent call last):\n",
+        "  File \"<input>\", line 5, in <module>\n",
+        "  File \"/usr/lib/python3.12/json/__init__.py\", line 346, in loads\n",
+        "    return _default_decoder.decode(s)\n",
+        "           ^^^^^^^^^^^^^^^^^^^^^^^^^^\n",
+        "json.decoder.JSONDecodeError: Expecting value: line 1 column 1 (char 0)\n",
+        "\n",
+        "The above exception was the direct cause of the following exception:\n",
+        "\n",
+        "Traceback (most recent call last):\n",
+        "  File \"<input>\", line 7, in <module>\n",
+        "RuntimeError: could not load config\n",
+    );
+
+    let exception = parse_traceback(text).unwrap();
+    assert_eq!(exception.exc_type, "RuntimeError");
+
+    let chain: Vec<_> = exception
+        .cause_chain
+        .iter()
+        .map(|chained| (chained.exception.exc_type.as_str(), chained.relation))
+        .collect();
+    assert_eq!(
+        chain,
+        vec![
+            ("json.decoder.JSONDecodeError", ChainRelation::Cause),
+            ("ValueError", ChainRelation::Context),
+        ]
+    );
+
+    let decode_error = &exception.cause_chain[0].exception;
+    assert_eq!(decode_error.frames.len(), 2);
+    assert_eq!(decode_error.frames[1].function.as_deref(), Some("loads"));
+    assert_eq!(decode_error.frames[1].column, Some(8));
+    assert!(decode_error.cause_chain.is_empty());
+}
+
+#[test]
+fn test_syntax_error_without_traceback() {
+    let text = concat!(
+        "  File \"<input>\", line 1\n",
+        "    print(\"a\"\n",
+        "         ^\n",
+        "SyntaxError: '(' was never closed\n",
+    );
+
+    let exception = parse_traceback(text).unwrap();
+    assert_eq!(exception.exc_type, "SyntaxError");
+    assert_eq!(exception.message, "'(' was never closed");
+    assert_eq!(
+        exception.frames,
+        vec![Frame {
+            file: "<input>".to_string(),
+            line: 1,
+            function: None,
+            source: Some("print(\"a\"".to_string()),
+            column: Some(6),
+        }]
+    );
+}
+
+#[test]
+fn test_syntax_error_in_traceback() {
+    let text = concat!(
+        "Traceback (most recent call last):\n",
+        "  File \"<input>\", line 1, in <module>\n",
+        "    compile('x = = 2', '<string>', 'exec')\n",
+        "  File \"<string>\", line 1\n",
+        "    x = = 2\n",
+        "        ^\n",
+        "SyntaxError: invalid syntax\n",
+    );
+
+    let exception = parse_traceback(text).unwrap();
+    assert_eq!(exception.frames.len(), 2);
+    let location = &exception.frames[1];
+    assert_eq!(location.file, "<string>");
+    assert_eq!(location.function, None);
+    assert_eq!(location.source.as_deref(), Some("x = = 2"));
+    assert_eq!(location.column, Some(5));
+}
+
+#[test]
+fn test_non_tracebacks_are_rejected() {
+    assert_eq!(parse_traceback(""), None);
+    assert_eq!(parse_traceback("Timed out after 15s"), None);
+    assert_eq!(parse_traceback("Machine crashed: out of memory"), None);
+}
+
+#[test]
+fn test_exec_result_error() {
+    let result: ExecResult = serde_json::from_str(
+        r#"{"error":"Traceback (most recent call last):\n  File \"<input>\", line 1, in <module>\nNameError: name 'x' is not defined","runtime_ms":1}"#,
+    )
+    .unwrap();
+
+    let error = result.error().unwrap();
+    assert_eq!(error.status, ExecStatus::PythonException);
+    let exception = error.parse().unwrap();
+    assert_eq!(exception.exc_type, "NameError");
+    assert_eq!(exception.message, "name 'x' is not defined");
+
+    let result: ExecResult = serde_json::from_str(r#"{"value":"1","runtime_ms":1}"#).unwrap();
+    assert_eq!(result.error(), None);
+}
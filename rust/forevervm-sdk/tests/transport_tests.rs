@@ -0,0 +1,590 @@
+#![allow(clippy::result_large_err)]
+
+use common::builder;
+use forevervm_sdk::{
+    api::{
+        api_types::{ApiExecResultResponse, ExecResult, ExecResultType, ExecStatus, Instruction},
+        http_api::CreateMachineRequest,
+        id_types::InstructionSeq,
+        protocol::{MessageFromServer, MessageToServer, StandardOutput, StandardOutputStream},
+    },
+    client::{
+        builder::ForeverVMClientBuilder,
+        error::ClientError,
+        exec::ExecOptions,
+        transport::{
+            channel::{
+                ChannelConnection, ChannelListener, ChannelSocketTransport, MockHttpTransport,
+            },
+            HttpResponse,
+        },
+        CancellationToken,
+    },
+};
+use futures_util::StreamExt;
+use reqwest::{Method, StatusCode};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::{mpsc, oneshot};
+
+mod common;
+
+fn output(data: &str, seq: i64) -> StandardOutput {
+    StandardOutput {
+        stream: StandardOutputStream::Stdout,
+        data: data.to_string(),
+        seq: seq.into(),
+    }
+}
+
+fn value_result(value: &str) -> ExecResult {
+    ExecResult {
+        status: ExecStatus::Ok,
+        result: ExecResultType::Value {
+            value: Some(value.to_string()),
+            data: None,
+        },
+        runtime_ms: 3,
+    }
+}
+
+async fn expect_exec(connection: &mut ChannelConnection) -> (String, u32) {
+    match connection.recv::<MessageToServer>().await.unwrap() {
+        Some(MessageToServer::Exec {
+            instruction,
+            request_id,
+        }) => (instruction.code, request_id.0),
+        other => panic!("expected exec, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_repl_over_channel_transport() {
+    let (transport, mut listener) = ChannelSocketTransport::new();
+    let client = builder().socket_transport(transport).build().unwrap();
+
+    let server = tokio::spawn(async move {
+        let mut connection = listener.accept().await.unwrap();
+        assert_eq!(
+            connection.request.url.as_str(),
+            "wss://api.example.com/v1/machine/m/repl"
+        );
+        assert_eq!(
+            connection.request.headers["authorization"],
+            "Bearer id.secret"
+        );
+
+        connection
+            .send(&MessageFromServer::Connected {
+                machine_name: "m".to_string().into(),
+            })
+            .unwrap();
+
+        let (code, request_id) = expect_exec(&mut connection).await;
+        assert_eq!(code, "print(1); 2");
+
+        // A stale acknowledgement for another request is ignored.
+        connection
+            .send(&MessageFromServer::ExecReceived {
+                seq: InstructionSeq(99),
+                request_id: (request_id + 1).into(),
+            })
+            .unwrap();
+        connection
+            .send(&MessageFromServer::ExecReceived {
+                seq: InstructionSeq(7),
+                request_id: request_id.into(),
+            })
+            .unwrap();
+        connection
+            .send(&MessageFromServer::Output {
+                chunk: output("1", 0),
+                instruction_id: InstructionSeq(7),
+            })
+            .unwrap();
+        // Output for a different instruction is dropped.
+        connection
+            .send(&MessageFromServer::Output {
+                chunk: output("stale", 0),
+                instruction_id: InstructionSeq(6),
+            })
+            .unwrap();
+        connection
+            .send(&MessageFromServer::Result(ApiExecResultResponse {
+                instruction_id: InstructionSeq(7),
+                result: value_result("2"),
+            }))
+            .unwrap();
+
+        connection
+    });
+
+    let mut repl = client.repl(&"m".to_string().into()).await.unwrap();
+    assert_eq!(repl.machine_name.to_string(), "m");
+
+    let mut handle = repl.exec("print(1); 2").await.unwrap();
+    let mut outputs = Vec::new();
+    while let Some(chunk) = handle.next().await {
+        outputs.push(chunk.unwrap());
+    }
+    assert_eq!(outputs, vec![output("1", 0)]);
+    assert_eq!(handle.result().await.unwrap(), value_result("2"));
+
+    drop(server.await.unwrap());
+}
+
+#[tokio::test]
+async fn test_http_over_mock_transport() {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let recorded = requests.clone();
+    let transport = MockHttpTransport::new(move |request| {
+        recorded.lock().unwrap().push(request.clone());
+        Ok(HttpResponse::from_bytes(
+            StatusCode::OK,
+            r#"{"machine_name":"m-1"}"#,
+        ))
+    });
+    let client = builder().http_transport(transport).build().unwrap();
+
+    let response = client
+        .create_machine(CreateMachineRequest::default())
+        .await
+        .unwrap();
+    assert_eq!(response.machine_name.to_string(), "m-1");
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, Method::POST);
+    assert_eq!(requests[0].url.path(), "/v1/machine/new");
+    assert_eq!(requests[0].body.as_deref(), Some(&b"{}"[..]));
+}
+
+#[tokio::test]
+async fn test_exec_result_stream_reassembles_split_lines() {
+    let transport = MockHttpTransport::new(|_| {
+        let chunks = [
+            "{\"type\":\"output\",\"chunk\":{\"stream\":\"stdout\",\"data\":\"a\",\"seq\":0},",
+            "\"instruction_id\":1}\n{\"type\":\"result\",\"instruction_id\":1,",
+            "\"result\":{\"value\":\"'done'\",\"data\":null,\"runtime_ms\":5}}\n",
+        ];
+        let mut response = HttpResponse::from_bytes(StatusCode::OK, "");
+        response.body = futures_util::stream::iter(
+            chunks.map(|chunk| Ok(bytes::Bytes::from_static(chunk.as_bytes()))),
+        )
+        .boxed();
+        Ok(response)
+    });
+    let client = builder().http_transport(transport).build().unwrap();
+
+    let mut stream = client
+        .exec_result_stream(&"m".to_string().into(), InstructionSeq(1))
+        .await
+        .unwrap();
+
+    match stream.next().await {
+        Some(Ok(MessageFromServer::Output { chunk, .. })) => assert_eq!(chunk, output("a", 0)),
+        other => panic!("unexpected message: {other:?}"),
+    }
+    match stream.next().await {
+        Some(Ok(MessageFromServer::Result(result))) => {
+            assert_eq!(result.result.runtime_ms, 5)
+        }
+        other => panic!("unexpected message: {other:?}"),
+    }
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_mock_transport_error_status() {
+    let transport = MockHttpTransport::new(|_| {
+        Ok(HttpResponse::from_bytes(
+            StatusCode::NOT_FOUND,
+            r#"{"code":"MachineNotFound","id":null}"#,
+        ))
+    });
+    let client = builder().http_transport(transport).build().unwrap();
+
+    let err = client
+        .exec_result(&"m".to_string().into(), InstructionSeq(0))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Api error: Api error: { code: MachineNotFound, id: None }"
+    );
+}
+
+#[tokio::test]
+async fn test_ndjson_handles_split_characters_and_blank_lines() {
+    let body = "{\"a\":\"caf\u{e9}\"}\n\n{\"a\":\"x\"}";
+    let bytes = body.as_bytes();
+    // Split inside the two-byte encoding of `é`.
+    let split = body.find('\u{e9}').unwrap() + 1;
+
+    let mut response = HttpResponse::from_bytes(StatusCode::OK, "");
+    response.body = futures_util::stream::iter([
+        Ok(bytes::Bytes::copy_from_slice(&bytes[..split])),
+        Ok(bytes::Bytes::copy_from_slice(&bytes[split..])),
+    ])
+    .boxed();
+
+    let values: Vec<serde_json::Value> = response
+        .ndjson()
+        .map(|value| value.unwrap())
+        .collect()
+        .await;
+    assert_eq!(
+        values,
+        vec![
+            serde_json::json!({"a": "caf\u{e9}"}),
+            serde_json::json!({"a": "x"})
+        ]
+    );
+}
+
+/// An HTTP request's method, path and JSON body, as sent over the wire.
+type SentRequest = (Method, String, serde_json::Value);
+
+/// The request that interrupts machine `m`: a no-op instruction with the exec request's
+/// `interrupt` flag set.
+fn interrupt_request() -> SentRequest {
+    (
+        Method::POST,
+        "/v1/machine/m/exec".to_string(),
+        serde_json::json!({
+            "instruction": {"code": "pass", "timeout_seconds": 15},
+            "interrupt": true,
+        }),
+    )
+}
+
+/// The request that looks up instruction `seq` on machine `m` before interrupting it.
+fn instruction_check(seq: i64) -> SentRequest {
+    (
+        Method::GET,
+        format!("/v1/machine/m/instructions/{seq}"),
+        serde_json::Value::Null,
+    )
+}
+
+/// A client builder whose HTTP transport sends each request it is given to the returned
+/// receiver. Result streams never produce anything, and if `stall_exec` is set neither do
+/// responses to non-interrupting exec requests, so only a deadline or cancellation ends
+/// a wait. Instruction lookups report `status`.
+fn stalling_builder(
+    stall_exec: bool,
+    status: &'static str,
+) -> (ForeverVMClientBuilder, mpsc::UnboundedReceiver<SentRequest>) {
+    let (sent, requests) = mpsc::unbounded_channel();
+    let transport = MockHttpTransport::new(move |request| {
+        let path = request.url.path().to_string();
+        let body: serde_json::Value = match &request.body {
+            Some(body) => serde_json::from_slice(body).unwrap(),
+            None => serde_json::Value::Null,
+        };
+        let stall = path.ends_with("/stream-result")
+            || (stall_exec && path.ends_with("/exec") && body["interrupt"] == false);
+        let lookup = path.contains("/instructions/");
+        sent.send((request.method.clone(), path, body)).unwrap();
+
+        let mut response = if lookup {
+            let instruction = format!(
+                r#"{{"instruction":{{"seq":5,"code":"x","status":"{status}",
+                    "created_at":"2025-01-01T00:00:00Z","started_at":null,"finished_at":null,
+                    "runtime_ms":null,"result":null}}}}"#
+            );
+            HttpResponse::from_bytes(StatusCode::OK, instruction)
+        } else {
+            HttpResponse::from_bytes(StatusCode::OK, r#"{"instruction_seq":5}"#)
+        };
+        if stall {
+            response.body = futures_util::stream::pending().boxed();
+        }
+        Ok(response)
+    });
+
+    (builder().http_transport(transport), requests)
+}
+
+/// Returns the requests sent so far.
+fn drain(requests: &mut mpsc::UnboundedReceiver<SentRequest>) -> Vec<SentRequest> {
+    std::iter::from_fn(|| requests.try_recv().ok()).collect()
+}
+
+/// Waits for the next request, which may be sent from a background task.
+async fn next_request(requests: &mut mpsc::UnboundedReceiver<SentRequest>) -> SentRequest {
+    tokio::time::timeout(Duration::from_secs(5), requests.recv())
+        .await
+        .expect("no request was sent")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_exec_and_wait_cancelled() {
+    let (builder, mut requests) = stalling_builder(false, "running");
+    let client = builder.build().unwrap();
+
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        cancel.cancel();
+    });
+
+    let err = client
+        .exec_and_wait_with_options(
+            &"m".to_string().into(),
+            Instruction::new("while True: pass"),
+            &ExecOptions::cancellation(token),
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::Cancelled), "{err}");
+
+    let requests = drain(&mut requests);
+    let paths: Vec<_> = requests.iter().map(|(_, path, _)| path.as_str()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            "/v1/machine/m/exec",
+            "/v1/machine/m/exec/5/stream-result",
+            "/v1/machine/m/instructions/5",
+            "/v1/machine/m/exec",
+        ]
+    );
+    assert_eq!(requests[0].2["interrupt"], false);
+    assert_eq!(requests[2], instruction_check(5));
+    assert_eq!(requests[3], interrupt_request());
+}
+
+#[tokio::test]
+async fn test_exec_and_wait_deadline() {
+    let (builder, mut requests) = stalling_builder(false, "running");
+    let client = builder.build().unwrap();
+
+    let err = client
+        .exec_and_wait_with_options(
+            &"m".to_string().into(),
+            Instruction::new("while True: pass"),
+            &ExecOptions::timeout(Duration::from_millis(20)),
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::DeadlineExceeded), "{err}");
+    assert_eq!(drain(&mut requests).last(), Some(&interrupt_request()));
+}
+
+#[tokio::test]
+async fn test_exec_and_wait_skips_interrupt_when_finished() {
+    // The instruction finished just before the deadline, so interrupting the machine would
+    // hit whatever it runs next.
+    let (builder, mut requests) = stalling_builder(false, "completed");
+    let client = builder.build().unwrap();
+
+    let err = client
+        .exec_and_wait_with_options(
+            &"m".to_string().into(),
+            Instruction::new("x = 1"),
+            &ExecOptions::timeout(Duration::from_millis(20)),
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::DeadlineExceeded), "{err}");
+    assert_eq!(drain(&mut requests).last(), Some(&instruction_check(5)));
+}
+
+#[tokio::test]
+async fn test_stopped_options_send_nothing() {
+    let (builder, mut requests) = stalling_builder(false, "running");
+    let client = builder.build().unwrap();
+    let token = CancellationToken::new();
+    token.cancel();
+    let options = ExecOptions::cancellation(token);
+
+    let err = client
+        .exec_with_options(Instruction::new("x = 1"), None, &options)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::Cancelled), "{err}");
+
+    let err = client
+        .exec_and_wait_with_options(
+            &"m".to_string().into(),
+            Instruction::new("x = 1"),
+            &options,
+            |_| {},
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::Cancelled), "{err}");
+    assert!(drain(&mut requests).is_empty());
+}
+
+#[tokio::test]
+async fn test_exec_stopped_before_response_interrupts() {
+    let (builder, mut requests) = stalling_builder(true, "running");
+    let client = builder.build().unwrap();
+
+    // The instruction may have been queued even though its response never arrived.
+    let err = client
+        .exec_with_options(
+            Instruction::new("while True: pass"),
+            Some(&"m".to_string().into()),
+            &ExecOptions::timeout(Duration::from_millis(20)),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::DeadlineExceeded), "{err}");
+
+    let requests = drain(&mut requests);
+    assert_eq!(requests.len(), 2);
+    assert_eq!(
+        requests[0].2,
+        serde_json::json!({
+            "instruction": {"code": "while True: pass", "timeout_seconds": 15},
+            "interrupt": false,
+        })
+    );
+    assert_eq!(requests[1], interrupt_request());
+}
+
+/// Accepts a REPL connection to machine `m` and acknowledges its first instruction as
+/// seq 3 once `ack` resolves. No result is ever sent.
+async fn serve_unfinished_instruction(
+    listener: &mut ChannelListener,
+    ack: impl std::future::Future<Output = ()>,
+) -> ChannelConnection {
+    let mut connection = listener.accept().await.unwrap();
+    connection
+        .send(&MessageFromServer::Connected {
+            machine_name: "m".to_string().into(),
+        })
+        .unwrap();
+
+    let message = connection
+        .recv::<serde_json::Value>()
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        message,
+        serde_json::json!({
+            "type": "exec",
+            "instruction": {"code": "while True: pass", "timeout_seconds": 15},
+            "request_id": 0,
+        })
+    );
+
+    ack.await;
+    connection
+        .send(&MessageFromServer::ExecReceived {
+            seq: InstructionSeq(3),
+            request_id: 0.into(),
+        })
+        .unwrap();
+    connection
+}
+
+#[tokio::test]
+async fn test_repl_cancel_interrupts_machine() {
+    let (transport, mut listener) = ChannelSocketTransport::new();
+    let (builder, mut requests) = stalling_builder(false, "running");
+    let client = builder.socket_transport(transport).build().unwrap();
+
+    let server =
+        tokio::spawn(async move { serve_unfinished_instruction(&mut listener, async {}).await });
+
+    let mut repl = client.repl(&"m".to_string().into()).await.unwrap();
+    let token = CancellationToken::new();
+    let mut handle = repl
+        .exec_instruction_with_options(
+            Instruction::new("while True: pass"),
+            &ExecOptions::cancellation(token.clone()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(handle.instruction_seq(), InstructionSeq(3));
+
+    token.cancel();
+    let err = handle.next().await.unwrap().unwrap_err();
+    assert!(matches!(err, ClientError::Cancelled), "{err}");
+    assert_eq!(
+        drain(&mut requests),
+        vec![instruction_check(3), interrupt_request()]
+    );
+
+    // The machine is only interrupted once per handle.
+    let err = handle.result().await.unwrap_err();
+    assert!(matches!(err, ClientError::Cancelled), "{err}");
+    assert!(drain(&mut requests).is_empty());
+
+    drop(server.await.unwrap());
+}
+
+#[tokio::test]
+async fn test_repl_cancel_before_ack_interrupts_on_ack() {
+    let (transport, mut listener) = ChannelSocketTransport::new();
+    let (builder, mut requests) = stalling_builder(false, "running");
+    let client = builder.socket_transport(transport).build().unwrap();
+
+    let (cancelled, on_cancelled) = oneshot::channel();
+    let server = tokio::spawn(async move {
+        serve_unfinished_instruction(&mut listener, async {
+            on_cancelled.await.unwrap();
+        })
+        .await
+    });
+
+    let mut repl = client.repl(&"m".to_string().into()).await.unwrap();
+    let err = repl
+        .exec_instruction_with_options(
+            Instruction::new("while True: pass"),
+            &ExecOptions::timeout(Duration::from_millis(20)),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::DeadlineExceeded), "{err}");
+    assert!(drain(&mut requests).is_empty());
+
+    // The acknowledgement arrives after the caller has given up.
+    cancelled.send(()).unwrap();
+    assert_eq!(next_request(&mut requests).await, instruction_check(3));
+    assert_eq!(next_request(&mut requests).await, interrupt_request());
+
+    drop(server.await.unwrap());
+}
+
+#[tokio::test]
+async fn test_repl_stopped_options_send_nothing() {
+    let (transport, mut listener) = ChannelSocketTransport::new();
+    let (builder, mut requests) = stalling_builder(false, "running");
+    let client = builder.socket_transport(transport).build().unwrap();
+
+    // The server checks that the first message it gets is the second exec, with the first
+    // request id.
+    let server =
+        tokio::spawn(async move { serve_unfinished_instruction(&mut listener, async {}).await });
+
+    let mut repl = client.repl(&"m".to_string().into()).await.unwrap();
+    let token = CancellationToken::new();
+    token.cancel();
+    let err = repl
+        .exec_instruction_with_options(Instruction::new("x = 1"), &ExecOptions::cancellation(token))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ClientError::Cancelled), "{err}");
+
+    let handle = repl
+        .exec_instruction(Instruction::new("while True: pass"))
+        .await
+        .unwrap();
+    assert_eq!(handle.instruction_seq(), InstructionSeq(3));
+    assert!(drain(&mut requests).is_empty());
+
+    drop(server.await.unwrap());
+}
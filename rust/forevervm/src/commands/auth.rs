@@ -29,7 +29,8 @@ pub async fn whoami() -> anyhow::Result<()> {
 pub async fn signup(base_url: Url) -> anyhow::Result<()> {
     let config_manager = ConfigManager::new()?;
     let config = config_manager.load()?;
-    if config.token.is_some() {
+    let store = config_manager.credential_store(&config);
+    if store.get(&config.server_url()?)?.is_some() {
         println!("Already logged in");
         return Ok(());
     }
@@ -117,17 +118,33 @@ pub async fn signup(base_url: Url) -> anyhow::Result<()> {
     }
 }
 
-pub async fn login(base_url: Url) -> anyhow::Result<()> {
+pub async fn login(base_url: Url, credential_helper: Option<String>) -> anyhow::Result<()> {
     let config_manager = ConfigManager::new()?;
-    let config = config_manager.load()?;
+    let mut config = config_manager.load()?;
+    let current_url = config.server_url()?;
+
+    // Look for an existing token where it is currently stored, but check and store the
+    // new one with the requested helper. The helper is only saved once that succeeds.
+    if current_url != base_url
+        && config_manager
+            .credential_store(&config)
+            .get(&current_url)?
+            .is_some()
+    {
+        println!("There is an existing token for another server. It will be replaced.")
+    }
+    if let Some(credential_helper) = credential_helper {
+        config.credential_helper = Some(credential_helper);
+    }
+    let store = config_manager.credential_store(&config);
 
-    if config.server_url()? == base_url {
-        if let Some(token) = &config.token {
-            let client = ForeverVMClient::new(base_url.clone(), token.clone());
+    if current_url == base_url {
+        if let Some(token) = store.get(&base_url)? {
+            let client = ForeverVMClient::new(base_url.clone(), token);
             match client.whoami().await {
                 Ok(whoami) => {
                     println!("Already logged in as {}", whoami.account.b_green());
-                    return Ok(());
+                    return save_login(&config_manager, base_url, config.credential_helper);
                 }
                 Err(err) => {
                     println!("There is an existing token, but it gives an error: {}", err);
@@ -135,8 +152,6 @@ pub async fn login(base_url: Url) -> anyhow::Result<()> {
                 }
             }
         }
-    } else if config.token.is_some() {
-        println!("There is an existing token for another server. It will be replaced.")
     }
 
     let token = Password::new().with_prompt("Enter your token").interact()?;
@@ -153,26 +168,40 @@ pub async fn login(base_url: Url) -> anyhow::Result<()> {
         }
     }
 
-    let mut config = config;
-    config.token = Some(token);
-    config.server_url = Some(base_url);
-    config_manager.save(&config)?;
+    store.store(&base_url, &token)?;
+    save_login(&config_manager, base_url, config.credential_helper)
+}
 
-    Ok(())
+/// Records a successful login in the config file.
+fn save_login(
+    config_manager: &ConfigManager,
+    server_url: Url,
+    credential_helper: Option<String>,
+) -> anyhow::Result<()> {
+    // Reload, since the file credential store writes to the same file.
+    let mut config = config_manager.load()?;
+    config.server_url = Some(server_url);
+    if credential_helper.is_some() {
+        config.credential_helper = credential_helper;
+        // Don't leave a plaintext token behind once a helper is in use.
+        config.token = None;
+    }
+    config_manager.save(&config)
 }
 
 pub async fn logout() -> anyhow::Result<()> {
     let config_manager = ConfigManager::new()?;
-    let mut config = config_manager.load()?;
+    let config = config_manager.load()?;
+    let server_url = config.server_url()?;
+    let store = config_manager.credential_store(&config);
 
-    if config.token.is_none() {
+    if store.get(&server_url)?.is_none() {
         println!("Not currently logged in");
         return Ok(());
     }
 
     // Clear the token
-    config.token = None;
-    config_manager.save(&config)?;
+    store.erase(&server_url)?;
     println!("Successfully logged out");
     Ok(())
 }
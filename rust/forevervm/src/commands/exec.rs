@@ -0,0 +1,31 @@
+use super::tail::stream_instruction;
+use crate::{config::ConfigManager, util::timeout_seconds};
+use chrono::Duration;
+use colorize::AnsiColor;
+use forevervm_sdk::api::{api_types::Instruction, id_types::MachineName};
+
+/// Runs code on a machine, or on a new one if none is given, printing its output as it
+/// arrives. Returns the exit code that reflects how the instruction ended.
+pub async fn exec(
+    code: String,
+    machine_name: Option<MachineName>,
+    timeout: Duration,
+) -> anyhow::Result<i32> {
+    let client = ConfigManager::new()?.client()?;
+
+    let instruction = Instruction {
+        code,
+        timeout_seconds: timeout_seconds(timeout)?,
+    };
+    let job = client
+        .submit_job(instruction, machine_name.as_ref())
+        .await?;
+    if machine_name.is_none() {
+        eprintln!(
+            "Running on new machine {}",
+            job.machine.to_string().b_green()
+        );
+    }
+
+    stream_instruction(&client, &job.machine, job.seq).await
+}
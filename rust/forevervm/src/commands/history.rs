@@ -0,0 +1,98 @@
+use crate::config::ConfigManager;
+use colorize::AnsiColor;
+use forevervm_sdk::api::{
+    api_types::{ApiInstruction, ExecResultType, InstructionStatus},
+    http_api::{ListInstructionsRequest, SortOrder},
+    id_types::MachineName,
+    protocol::StandardOutputStream,
+};
+use futures_util::{StreamExt, TryStreamExt};
+
+pub async fn history(
+    machine_name: MachineName,
+    limit: usize,
+    show_output: bool,
+) -> anyhow::Result<()> {
+    let client = ConfigManager::new()?.client()?;
+
+    // Fetch newest first so that only the last `limit` instructions are requested, then
+    // print them in the order they ran.
+    let request = ListInstructionsRequest {
+        include_output: show_output,
+        sort_order: Some(SortOrder::Desc),
+        limit: Some(limit.try_into().unwrap_or(u32::MAX)),
+        ..Default::default()
+    };
+    let mut instructions: Vec<ApiInstruction> = client
+        .instructions(&machine_name, request)
+        .take(limit)
+        .try_collect()
+        .await?;
+    instructions.reverse();
+
+    if instructions.is_empty() {
+        println!("No instructions on {}", machine_name.to_string().b_green());
+        return Ok(());
+    }
+
+    for instruction in instructions {
+        print_instruction(instruction, show_output);
+    }
+
+    Ok(())
+}
+
+/// An instruction's status, colored by outcome.
+pub fn status_label(status: InstructionStatus) -> String {
+    match status {
+        InstructionStatus::Pending => "pending".b_yellow(),
+        InstructionStatus::Running => "running".b_cyan(),
+        InstructionStatus::Completed => "completed".b_green(),
+        InstructionStatus::Error => "error".b_red(),
+        InstructionStatus::Interrupted => "interrupted".b_red(),
+        InstructionStatus::Unknown => "unknown".to_string(),
+    }
+}
+
+fn print_instruction(instruction: ApiInstruction, show_output: bool) {
+    let status = status_label(instruction.status);
+    let runtime = instruction
+        .runtime_ms
+        .map(|ms| format!(" ({ms} ms)"))
+        .unwrap_or_default();
+
+    println!(
+        "{} {} {}{}",
+        format!("#{}", instruction.seq).b_green(),
+        status,
+        instruction.created_at.format("%Y-%m-%d %H:%M:%S"),
+        runtime
+    );
+
+    for (i, line) in instruction.code.lines().enumerate() {
+        let prompt = if i == 0 { ">>>" } else { "..." };
+        println!("  {} {}", prompt.b_yellow(), line);
+    }
+
+    if show_output {
+        for chunk in &instruction.output {
+            for line in chunk.data.lines() {
+                let line = match chunk.stream {
+                    StandardOutputStream::Stdout => line.to_string(),
+                    StandardOutputStream::Stderr => line.to_string().red(),
+                };
+                println!("  {line}");
+            }
+        }
+    }
+
+    match instruction.result.map(|result| result.result) {
+        Some(ExecResultType::Value {
+            value: Some(value), ..
+        }) => println!("  {value}"),
+        Some(ExecResultType::Error { error }) => println!("  {}", error.red()),
+        _ => {}
+    }
+
+    println!();
+}
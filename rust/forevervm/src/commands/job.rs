@@ -0,0 +1,120 @@
+use super::{
+    history::status_label,
+    tail::{print_result, stream_instruction},
+};
+use crate::{
+    config::ConfigManager,
+    jobs::{JobRecord, JobStore},
+    util::{timeout_seconds, EXIT_INSTRUCTION_ERROR, EXIT_INSTRUCTION_INTERRUPTED},
+};
+use chrono::{Duration, Utc};
+use colorize::AnsiColor;
+use forevervm_sdk::{
+    api::{
+        api_types::{ApiInstruction, Instruction, InstructionStatus},
+        id_types::MachineName,
+    },
+    client::job::JobHandle,
+};
+
+pub async fn job_submit(
+    code: String,
+    machine_name: Option<MachineName>,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    let config_manager = ConfigManager::new()?;
+    let client = config_manager.client()?;
+
+    let instruction = Instruction {
+        code: code.clone(),
+        timeout_seconds: timeout_seconds(timeout)?,
+    };
+    let job = client
+        .submit_job(instruction, machine_name.as_ref())
+        .await?;
+
+    JobStore::new(&config_manager).add(JobRecord {
+        job: job.clone(),
+        code,
+        submitted_at: Utc::now(),
+    })?;
+
+    eprintln!("Submitted job {}", job.to_string().b_green());
+    // The bare handle goes to stdout so that scripts can capture it.
+    println!("{job}");
+
+    Ok(())
+}
+
+/// Prints the status of `job`, or of every locally recorded job if none is given.
+pub async fn job_status(job: Option<JobHandle>) -> anyhow::Result<()> {
+    let config_manager = ConfigManager::new()?;
+    let client = config_manager.client()?;
+
+    if let Some(job) = job {
+        let instruction = job.poll(&client).await?;
+        print_job(&job, &instruction);
+        return Ok(());
+    }
+
+    let records = JobStore::new(&config_manager).load()?;
+    if records.is_empty() {
+        println!("No jobs submitted from this computer");
+        return Ok(());
+    }
+
+    for record in records {
+        match record.job.poll(&client).await {
+            Ok(instruction) => print_job(&record.job, &instruction),
+            Err(err) => println!(
+                "{} {} {}",
+                record.job.to_string().b_green(),
+                "unavailable".b_red(),
+                err
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Streams a job's output from the start until it finishes, and returns the exit code
+/// that reflects its result.
+pub async fn job_logs(job: JobHandle) -> anyhow::Result<i32> {
+    let client = ConfigManager::new()?.client()?;
+    stream_instruction(&client, &job.machine, job.seq).await
+}
+
+/// Waits for a job to finish and prints its result, returning the exit code that
+/// reflects it.
+pub async fn job_wait(job: JobHandle, timeout: Duration) -> anyhow::Result<i32> {
+    let client = ConfigManager::new()?.client()?;
+    let instruction = job.wait(&client, timeout.to_std()?).await?;
+
+    Ok(match instruction.result {
+        Some(result) => print_result(result),
+        // Without a result, all that's known is how the instruction ended.
+        None => match instruction.status {
+            InstructionStatus::Completed => 0,
+            InstructionStatus::Interrupted => EXIT_INSTRUCTION_INTERRUPTED,
+            _ => EXIT_INSTRUCTION_ERROR,
+        },
+    })
+}
+
+fn print_job(job: &JobHandle, instruction: &ApiInstruction) {
+    let runtime = instruction
+        .runtime_ms
+        .map(|ms| format!(" ({ms} ms)"))
+        .unwrap_or_default();
+    let code = instruction.code.lines().next().unwrap_or_default();
+
+    println!(
+        "{} {} {}{} {}",
+        job.to_string().b_green(),
+        status_label(instruction.status),
+        instruction.created_at.format("%Y-%m-%d %H:%M:%S"),
+        runtime,
+        code
+    );
+}
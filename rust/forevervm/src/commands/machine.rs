@@ -1,15 +1,31 @@
 use crate::{config::ConfigManager, util::ApproximateDuration};
-use chrono::Utc;
+use chrono::{Duration, Utc};
+use clap::ValueEnum;
 use colorize::AnsiColor;
-use forevervm_sdk::api::http_api::{CreateMachineRequest, ListMachinesRequest};
+use dialoguer::{theme::ColorfulTheme, Confirm};
+use forevervm_sdk::api::{
+    api_types::{ApiMachine, MachineEventKind},
+    http_api::{CreateMachineRequest, ListMachinesRequest, MachineEventsRequest},
+    id_types::MachineName,
+};
+use futures_util::StreamExt;
+use std::collections::HashMap;
 
-pub async fn machine_list(tags: std::collections::HashMap<String, String>) -> anyhow::Result<()> {
+pub async fn machine_list(
+    mut request: ListMachinesRequest,
+    limit: Option<usize>,
+) -> anyhow::Result<()> {
     let client = ConfigManager::new()?.client()?;
-    let request = ListMachinesRequest { tags };
-    let machines = client.list_machines(request).await?;
+    if let Some(limit) = limit {
+        // No point fetching pages larger than what will be shown.
+        request.limit = Some(limit.try_into().unwrap_or(u32::MAX));
+    }
+
+    let mut machines = client.machines(request).take(limit.unwrap_or(usize::MAX));
 
     println!("Machines:");
-    for machine in machines.machines {
+    while let Some(machine) = machines.next().await {
+        let machine = machine?;
         let expires_at = if let Some(expires_at) = machine.expires_at {
             expires_at.to_string()
         } else {
@@ -33,6 +49,15 @@ pub async fn machine_list(tags: std::collections::HashMap<String, String>) -> an
         println!("  Expires: {}", expires_at.b_yellow());
         println!("  Status:  {}", status.b_yellow());
         println!("  Running: {}", machine.running.to_string().b_yellow());
+        if let Some(memory_mb) = machine.memory_mb {
+            println!("  Memory:  {}", format!("{memory_mb} MB").b_yellow());
+        }
+        if let Some(vcpus) = machine.vcpus {
+            println!("  vCPUs:   {}", vcpus.to_string().b_yellow());
+        }
+        if let Some(disk_mb) = machine.disk_mb {
+            println!("  Disk:    {}", format!("{disk_mb} MB").b_yellow());
+        }
         for (key, value) in machine.tags.into_iter() {
             println!("  Tag: {} = {}", key.b_yellow(), value.b_yellow());
         }
@@ -42,12 +67,21 @@ pub async fn machine_list(tags: std::collections::HashMap<String, String>) -> an
     Ok(())
 }
 
-pub async fn machine_new(tags: std::collections::HashMap<String, String>) -> anyhow::Result<()> {
+pub async fn machine_new(
+    tags: HashMap<String, String>,
+    ttl: Option<Duration>,
+    memory_mb: Option<u32>,
+) -> anyhow::Result<()> {
     let client = ConfigManager::new()?.client()?;
 
+    let ttl_seconds = ttl
+        .map(|ttl| u64::try_from(ttl.num_seconds()))
+        .transpose()?;
     let request = CreateMachineRequest {
         tags,
-        memory_mb: None,
+        memory_mb,
+        ttl_seconds,
+        ..Default::default()
     };
     let machine = client.create_machine(request).await?;
 
@@ -58,3 +92,241 @@ pub async fn machine_new(tags: std::collections::HashMap<String, String>) -> any
 
     Ok(())
 }
+
+pub async fn machine_extend(machine_name: MachineName, by: Duration) -> anyhow::Result<()> {
+    let client = ConfigManager::new()?.client()?;
+    let machine = client.extend_expiry(&machine_name, by.to_std()?).await?;
+
+    let expires_at = match machine.expires_at {
+        Some(expires_at) => expires_at.to_string(),
+        None => "never".to_string(),
+    };
+    println!(
+        "Machine {} now expires at {}",
+        machine.name.to_string().b_green(),
+        expires_at.b_yellow()
+    );
+
+    Ok(())
+}
+
+pub async fn machine_suspend(machine_name: MachineName) -> anyhow::Result<()> {
+    let client = ConfigManager::new()?.client()?;
+    let machine = client.suspend_machine(&machine_name).await?;
+
+    println!("Suspended machine {}", machine.name.to_string().b_green());
+
+    Ok(())
+}
+
+pub async fn machine_resume(machine_name: MachineName, timeout: Duration) -> anyhow::Result<()> {
+    let client = ConfigManager::new()?.client()?;
+    let machine = client
+        .resume_machine(&machine_name, timeout.to_std()?)
+        .await?;
+
+    println!("Machine {} is running", machine.name.to_string().b_green());
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum WaitCondition {
+    /// The machine has no pending instruction
+    Idle,
+    /// The machine is running
+    Running,
+}
+
+pub async fn machine_wait(
+    machine_name: MachineName,
+    until: WaitCondition,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    let client = ConfigManager::new()?.client()?;
+    let timeout = timeout.to_std()?;
+
+    let (machine, state) = match until {
+        WaitCondition::Idle => (client.wait_idle(&machine_name, timeout).await?, "idle"),
+        WaitCondition::Running => (
+            client.wait_running(&machine_name, timeout).await?,
+            "running",
+        ),
+    };
+
+    println!(
+        "Machine {} is {}",
+        machine.name.to_string().b_green(),
+        state
+    );
+
+    Ok(())
+}
+
+pub async fn machine_resize(machine_name: MachineName, memory_mb: u32) -> anyhow::Result<()> {
+    let client = ConfigManager::new()?.client()?;
+    let machine = client.resize_machine(&machine_name, memory_mb).await?;
+
+    let memory = match machine.memory_mb {
+        Some(memory_mb) => format!("{memory_mb} MB"),
+        None => format!("{memory_mb} MB (requested)"),
+    };
+    println!(
+        "Resized machine {} to {}",
+        machine.name.to_string().b_green(),
+        memory.b_yellow()
+    );
+
+    Ok(())
+}
+
+pub async fn machine_events(filter: MachineEventsRequest) -> anyhow::Result<()> {
+    let client = ConfigManager::new()?.client()?;
+    let mut events = client.machine_events(filter).await?;
+
+    while let Some(event) = events.next().await {
+        let event = event?;
+        // Pad before coloring, so that escape codes don't throw off the alignment.
+        let kind = match event.kind {
+            MachineEventKind::Created => format!("{:<11}", "created").b_green(),
+            MachineEventKind::Started => format!("{:<11}", "started").b_green(),
+            MachineEventKind::InstructionReceived => format!("{:<11}", "instruction").b_cyan(),
+            MachineEventKind::Idle => format!("{:<11}", "idle").b_yellow(),
+            MachineEventKind::Suspended => format!("{:<11}", "suspended").b_yellow(),
+            MachineEventKind::Expired => format!("{:<11}", "expired").b_red(),
+            MachineEventKind::Deleted => format!("{:<11}", "deleted").b_red(),
+            MachineEventKind::Unknown => format!("{:<11}", "unknown"),
+        };
+
+        println!(
+            "{} {} {}",
+            event.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            kind,
+            event.machine_name
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn machine_tag(
+    machine_name: MachineName,
+    set: HashMap<String, String>,
+    remove: Vec<String>,
+) -> anyhow::Result<()> {
+    let client = ConfigManager::new()?.client()?;
+    let machine = client.update_tags(&machine_name, set, remove).await?;
+
+    println!("Updated tags on {}", machine.name.to_string().b_green());
+    let mut tags: Vec<_> = machine.tags.into_iter().collect();
+    tags.sort();
+    for (key, value) in tags {
+        println!("  Tag: {} = {}", key.b_yellow(), value.b_yellow());
+    }
+
+    Ok(())
+}
+
+pub async fn machine_delete(machine_names: Vec<MachineName>) -> anyhow::Result<()> {
+    let client = ConfigManager::new()?.client()?;
+
+    let mut failed = 0;
+    for machine_name in machine_names {
+        match client.delete_machine(&machine_name).await {
+            Ok(()) => println!("Deleted machine {}", machine_name.to_string().b_green()),
+            Err(err) => {
+                eprintln!(
+                    "Failed to delete machine {}: {}",
+                    machine_name.to_string().b_red(),
+                    err
+                );
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("Failed to delete {failed} machine(s)");
+    }
+
+    Ok(())
+}
+
+pub struct PruneOptions {
+    pub tags: HashMap<String, String>,
+    pub older_than: Option<Duration>,
+    pub idle: bool,
+    pub dry_run: bool,
+    pub yes: bool,
+}
+
+impl PruneOptions {
+    fn matches(&self, machine: &ApiMachine) -> bool {
+        let old_enough = self
+            .older_than
+            .is_none_or(|older_than| Utc::now() - machine.created_at >= older_than);
+        let idle = !self.idle || (!machine.running && !machine.has_pending_instruction);
+        let tagged = self
+            .tags
+            .iter()
+            .all(|(key, value)| machine.tags.get(key) == Some(value));
+
+        old_enough && idle && tagged
+    }
+}
+
+pub async fn machine_prune(options: PruneOptions) -> anyhow::Result<()> {
+    let client = ConfigManager::new()?.client()?;
+
+    let request = ListMachinesRequest {
+        tags: options.tags.clone(),
+        running: options.idle.then_some(false),
+        has_pending_instruction: options.idle.then_some(false),
+        ..Default::default()
+    };
+
+    // The filters are re-checked locally so that nothing is deleted that the user didn't ask
+    // for, even if the server ignores a filter.
+    let mut targets = Vec::new();
+    let mut machines = client.machines(request);
+    while let Some(machine) = machines.next().await {
+        let machine = machine?;
+        if options.matches(&machine) {
+            targets.push(machine);
+        }
+    }
+    drop(machines);
+
+    if targets.is_empty() {
+        println!("No machines to prune.");
+        return Ok(());
+    }
+
+    println!("Machines to delete:");
+    for machine in &targets {
+        let age = ApproximateDuration::from(Utc::now() - machine.created_at);
+        println!(
+            "  {} (created {} ago)",
+            machine.name.to_string().b_green(),
+            age.to_string().b_yellow()
+        );
+    }
+
+    if options.dry_run {
+        println!("Dry run: would delete {} machine(s).", targets.len());
+        return Ok(());
+    }
+
+    if !options.yes {
+        let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!("Delete {} machine(s)?", targets.len()))
+            .default(false)
+            .interact()?;
+        if !confirmed {
+            println!("Aborted.");
+            return Ok(());
+        }
+    }
+
+    machine_delete(targets.into_iter().map(|machine| machine.name).collect()).await
+}
@@ -1,3 +1,7 @@
 pub mod auth;
+pub mod exec;
+pub mod history;
+pub mod job;
 pub mod machine;
 pub mod repl;
+pub mod tail;
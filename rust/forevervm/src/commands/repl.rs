@@ -1,4 +1,5 @@
-use crate::config::ConfigManager;
+use crate::{config::ConfigManager, util::ApproximateDuration};
+use chrono::Utc;
 use colorize::AnsiColor;
 use forevervm_sdk::api::{
     api_types::{ExecResultType, Instruction},
@@ -27,6 +28,25 @@ pub async fn machine_repl(
 
     println!("Connected to {}", machine_name.to_string().b_green());
 
+    // Best effort: failing to fetch the machine shouldn't prevent using the REPL.
+    if let Ok(machine) = client.get_machine(&machine_name).await {
+        if let Some(expires_at) = machine.expires_at {
+            let remaining = expires_at - Utc::now();
+            if remaining < chrono::Duration::hours(1) {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "Warning: this machine expires in {} ({}). Extend it with `forevervm machine extend {} --by 1d`.",
+                        ApproximateDuration::from(remaining.max(chrono::Duration::zero())),
+                        expires_at,
+                        machine_name
+                    )
+                    .b_yellow()
+                );
+            }
+        }
+    }
+
     let mut rl = DefaultEditor::new()?;
 
     loop {
@@ -45,7 +65,7 @@ pub async fn machine_repl(
                 match result {
                     Ok(mut result) => {
                         while let Some(output) = result.next().await {
-                            println!("{}", output.data);
+                            println!("{}", output?.data);
                         }
 
                         let result = result.result().await;
@@ -0,0 +1,98 @@
+use crate::{config::ConfigManager, util::exit_code};
+use colorize::AnsiColor;
+use forevervm_sdk::{
+    api::{
+        api_types::{ExecResult, ExecResultType},
+        http_api::{ListInstructionsRequest, SortOrder},
+        id_types::{InstructionSeq, MachineName},
+        protocol::{MessageFromServer, StandardOutputStream},
+    },
+    client::ForeverVMClient,
+};
+use futures_util::StreamExt;
+use std::io::Write;
+
+/// Streams the output of an instruction until it finishes, and returns the exit code that
+/// reflects its result.
+pub async fn tail(
+    machine_name: MachineName,
+    instruction_seq: Option<InstructionSeq>,
+) -> anyhow::Result<i32> {
+    let client = ConfigManager::new()?.client()?;
+
+    let instruction_seq = match instruction_seq {
+        Some(seq) => seq,
+        None => {
+            let request = ListInstructionsRequest {
+                sort_order: Some(SortOrder::Desc),
+                limit: Some(1),
+                ..Default::default()
+            };
+            let latest = client
+                .list_instructions(&machine_name, request)
+                .await?
+                .instructions
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("No instructions on {machine_name}"))?;
+            latest.seq
+        }
+    };
+
+    eprintln!(
+        "{}",
+        format!("Tailing instruction #{instruction_seq} on {machine_name}").b_yellow()
+    );
+
+    stream_instruction(&client, &machine_name, instruction_seq).await
+}
+
+/// Prints an instruction's output as it arrives, followed by its result, and returns the
+/// exit code that reflects the result.
+pub async fn stream_instruction(
+    client: &ForeverVMClient,
+    machine_name: &MachineName,
+    instruction_seq: InstructionSeq,
+) -> anyhow::Result<i32> {
+    let mut stream = client
+        .exec_result_stream(machine_name, instruction_seq)
+        .await?;
+    while let Some(message) = stream.next().await {
+        match message? {
+            MessageFromServer::Output { chunk, .. } => match chunk.stream {
+                StandardOutputStream::Stdout => {
+                    print!("{}", chunk.data);
+                    std::io::stdout().flush()?;
+                }
+                StandardOutputStream::Stderr => {
+                    eprint!("{}", chunk.data.red());
+                }
+            },
+            MessageFromServer::Result(result) => return Ok(print_result(result.result)),
+            MessageFromServer::Error(err) => return Err(err.into()),
+            MessageFromServer::Message { message, .. } => {
+                eprintln!("{}", message.b_yellow());
+            }
+            _ => {}
+        }
+    }
+
+    // The instruction may well still be running; only the stream of its output ended.
+    Err(anyhow::anyhow!(
+        "Lost the output stream of instruction #{instruction_seq} before it finished"
+    ))
+}
+
+/// Prints an instruction's value or error, and returns the exit code that reflects how it
+/// ended.
+pub fn print_result(result: ExecResult) -> i32 {
+    match result.result {
+        ExecResultType::Value {
+            value: Some(value), ..
+        } => println!("{value}"),
+        ExecResultType::Value { value: None, .. } => {}
+        ExecResultType::Error { error } => eprintln!("{}", error.red()),
+    }
+
+    exit_code(result.status)
+}
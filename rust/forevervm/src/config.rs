@@ -1,4 +1,7 @@
-use crate::DEFAULT_SERVER_URL;
+use crate::{
+    credentials::{CredentialStore, FileCredentialStore, HelperCredentialStore},
+    DEFAULT_SERVER_URL,
+};
 use anyhow::{Context, Result};
 use dirs::home_dir;
 use forevervm_sdk::{api::token::ApiToken, client::ForeverVMClient};
@@ -10,6 +13,11 @@ use url::Url;
 pub struct Config {
     pub token: Option<ApiToken>,
     pub server_url: Option<Url>,
+
+    /// External program used to store the API token instead of this file.
+    /// See [`crate::credentials`] for the protocol.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_helper: Option<String>,
 }
 
 impl Config {
@@ -39,13 +47,22 @@ impl ConfigManager {
 
     pub fn client(&self) -> Result<ForeverVMClient> {
         let config = self.load()?;
-        if let Some(token) = &config.token {
-            Ok(ForeverVMClient::new(config.server_url()?, token.clone()))
+        let server_url = config.server_url()?;
+        if let Some(token) = self.credential_store(&config).get(&server_url)? {
+            Ok(ForeverVMClient::new(server_url, token))
         } else {
             Err(anyhow::anyhow!("Not logged in"))
         }
     }
 
+    /// Returns the configured credential helper, or the config file itself if none is set.
+    pub fn credential_store(&self, config: &Config) -> Box<dyn CredentialStore + '_> {
+        match &config.credential_helper {
+            Some(helper) => Box::new(HelperCredentialStore::new(helper.clone())),
+            None => Box::new(FileCredentialStore::new(self)),
+        }
+    }
+
     pub fn load(&self) -> Result<Config> {
         if !self.config_path.exists() {
             if let Some(parent) = self.config_path.parent() {
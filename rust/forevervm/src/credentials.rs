@@ -0,0 +1,294 @@
+//! Storage for API tokens.
+//!
+//! By default, tokens are stored in the config file. If `credential_helper` is set in the
+//! config, tokens are instead stored by an external program using a protocol modeled on
+//! git's credential helpers: the helper is invoked through the shell with `get`, `store`
+//! or `erase` appended as an argument, and receives `key=value` lines on stdin describing
+//! the server, terminated by a blank line:
+//!
+//! ```text
+//! protocol=https
+//! host=api.forevervm.com
+//! username=<token id>      (store only)
+//! password=<token>         (store only)
+//! ```
+//!
+//! For `get`, the helper writes `password=<token>` to stdout, or nothing if it has no
+//! token for the server.
+
+use crate::config::ConfigManager;
+use anyhow::{Context, Result};
+use forevervm_sdk::api::token::ApiToken;
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+use url::Url;
+
+pub trait CredentialStore {
+    /// Returns the stored token for `server_url`, if any.
+    fn get(&self, server_url: &Url) -> Result<Option<ApiToken>>;
+
+    /// Stores `token` for `server_url`, replacing any existing token.
+    fn store(&self, server_url: &Url, token: &ApiToken) -> Result<()>;
+
+    /// Removes the stored token for `server_url`.
+    fn erase(&self, server_url: &Url) -> Result<()>;
+}
+
+/// Stores the token in plaintext in the config file (readable only by the owner).
+pub struct FileCredentialStore<'a> {
+    config_manager: &'a ConfigManager,
+}
+
+impl<'a> FileCredentialStore<'a> {
+    pub fn new(config_manager: &'a ConfigManager) -> Self {
+        Self { config_manager }
+    }
+}
+
+impl CredentialStore for FileCredentialStore<'_> {
+    fn get(&self, server_url: &Url) -> Result<Option<ApiToken>> {
+        let config = self.config_manager.load()?;
+        if config.server_url()? != *server_url {
+            return Ok(None);
+        }
+
+        Ok(config.token)
+    }
+
+    fn store(&self, _server_url: &Url, token: &ApiToken) -> Result<()> {
+        let mut config = self.config_manager.load()?;
+        config.token = Some(token.clone());
+        self.config_manager.save(&config)
+    }
+
+    fn erase(&self, _server_url: &Url) -> Result<()> {
+        let mut config = self.config_manager.load()?;
+        config.token = None;
+        self.config_manager.save(&config)
+    }
+}
+
+/// Delegates token storage to an external credential helper program.
+pub struct HelperCredentialStore {
+    command: String,
+}
+
+impl HelperCredentialStore {
+    pub fn new(command: String) -> Self {
+        Self { command }
+    }
+
+    fn run(&self, action: &str, input: &str) -> Result<String> {
+        #[cfg(unix)]
+        let mut command = {
+            let mut command = Command::new("sh");
+            command
+                .arg("-c")
+                .arg(format!("{} \"$@\"", self.command))
+                .arg(&self.command)
+                .arg(action);
+            command
+        };
+
+        #[cfg(windows)]
+        let mut command = {
+            let mut command = Command::new("cmd");
+            command
+                .arg("/C")
+                .arg(format!("{} {}", self.command, action));
+            command
+        };
+
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .with_context(|| format!("Failed to run credential helper `{}`", self.command))?;
+
+        let write_result = child
+            .stdin
+            .take()
+            .context("Failed to open credential helper stdin")?
+            .write_all(input.as_bytes());
+        match write_result {
+            // Helpers are free to ignore their input and exit early.
+            Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => {}
+            result => result.context("Failed to write to credential helper")?,
+        }
+
+        let output = child
+            .wait_with_output()
+            .context("Failed to wait for credential helper")?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "Credential helper `{}` failed on `{}` ({})",
+                self.command,
+                action,
+                output.status
+            ));
+        }
+
+        String::from_utf8(output.stdout).context("Credential helper output is not valid UTF-8")
+    }
+}
+
+impl CredentialStore for HelperCredentialStore {
+    fn get(&self, server_url: &Url) -> Result<Option<ApiToken>> {
+        let output = self.run("get", &describe(server_url, None))?;
+
+        let password = output
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .find(|(key, _)| *key == "password")
+            .map(|(_, value)| value.to_string());
+
+        match password {
+            Some(password) => Ok(Some(
+                ApiToken::new(password).context("Credential helper returned an invalid token")?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    fn store(&self, server_url: &Url, token: &ApiToken) -> Result<()> {
+        self.run("store", &describe(server_url, Some(token)))?;
+        Ok(())
+    }
+
+    fn erase(&self, server_url: &Url) -> Result<()> {
+        self.run("erase", &describe(server_url, None))?;
+        Ok(())
+    }
+}
+
+/// Builds the helper's stdin: the server's attributes, optionally the token, and a blank line.
+fn describe(server_url: &Url, token: Option<&ApiToken>) -> String {
+    let mut host = server_url.host_str().unwrap_or_default().to_string();
+    if let Some(port) = server_url.port() {
+        host = format!("{host}:{port}");
+    }
+
+    let mut input = format!("protocol={}\nhost={}\n", server_url.scheme(), host);
+    if let Some(token) = token {
+        input.push_str(&format!(
+            "username={}\npassword={}\n",
+            token.id,
+            token.expose_secret()
+        ));
+    }
+    input.push('\n');
+    input
+}
+
+#[cfg(all(test, unix))]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use std::{os::unix::fs::PermissionsExt, path::Path};
+    use tempfile::TempDir;
+
+    /// Writes an executable shell script to a directory whose name contains a space, and
+    /// returns a helper command that invokes it with an extra argument.
+    fn helper(dir: &TempDir, script: &str) -> HelperCredentialStore {
+        let scripts = dir.path().join("helper scripts");
+        std::fs::create_dir_all(&scripts).unwrap();
+        let path = scripts.join("helper.sh");
+        std::fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        HelperCredentialStore::new(format!("'{}' --vault test", path.display()))
+    }
+
+    fn read(dir: &TempDir, name: &str) -> String {
+        std::fs::read_to_string(dir.path().join(name)).unwrap()
+    }
+
+    fn recording_script(dir: &Path) -> String {
+        format!(
+            "printf '%s\\n' \"$@\" > '{0}/args'\ncat > '{0}/input'",
+            dir.display()
+        )
+    }
+
+    fn server() -> Url {
+        Url::parse("https://api.example.com:8443").unwrap()
+    }
+
+    #[test]
+    fn test_store_sends_arguments_and_token() {
+        let dir = TempDir::new().unwrap();
+        let store = helper(&dir, &recording_script(dir.path()));
+        let token = ApiToken::new("id.secret".to_string()).unwrap();
+
+        store.store(&server(), &token).unwrap();
+
+        assert_eq!(read(&dir, "args"), "--vault\ntest\nstore\n");
+        assert_eq!(
+            read(&dir, "input"),
+            "protocol=https\nhost=api.example.com:8443\nusername=id\npassword=id.secret\n\n"
+        );
+    }
+
+    #[test]
+    fn test_get_and_erase_omit_the_token() {
+        let dir = TempDir::new().unwrap();
+        let store = helper(&dir, &recording_script(dir.path()));
+
+        assert!(store.get(&server()).unwrap().is_none());
+        assert_eq!(read(&dir, "args"), "--vault\ntest\nget\n");
+        assert_eq!(
+            read(&dir, "input"),
+            "protocol=https\nhost=api.example.com:8443\n\n"
+        );
+
+        store.erase(&server()).unwrap();
+        assert_eq!(read(&dir, "args"), "--vault\ntest\nerase\n");
+    }
+
+    #[test]
+    fn test_get_parses_password() {
+        let dir = TempDir::new().unwrap();
+        let store = helper(
+            &dir,
+            "cat > /dev/null\necho username=id\necho 'password=id.se=cret'\necho quit=1",
+        );
+
+        let token = store.get(&server()).unwrap().unwrap();
+        assert_eq!(token.id, "id");
+        assert_eq!(token.expose_secret(), "id.se=cret");
+    }
+
+    #[test]
+    fn test_get_rejects_invalid_token() {
+        let dir = TempDir::new().unwrap();
+        let store = helper(&dir, "echo password=no-separator");
+
+        let err = store.get(&server()).unwrap_err();
+        assert!(err.to_string().contains("invalid token"), "{err}");
+    }
+
+    #[test]
+    fn test_failing_helper_is_an_error() {
+        let dir = TempDir::new().unwrap();
+        let store = helper(&dir, "exit 3");
+
+        let err = store.erase(&server()).unwrap_err().to_string();
+        assert!(err.contains("failed on `erase`"), "{err}");
+    }
+
+    #[test]
+    fn test_helper_may_ignore_input() {
+        let dir = TempDir::new().unwrap();
+        let store = helper(&dir, "exec 0<&-\nexit 0");
+        let token = ApiToken::new("id.secret".to_string()).unwrap();
+
+        // The helper closes stdin without reading it, so writing may hit a broken pipe.
+        for _ in 0..10 {
+            store.store(&server(), &token).unwrap();
+        }
+    }
+}
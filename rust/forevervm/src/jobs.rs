@@ -0,0 +1,111 @@
+//! Local record of jobs submitted with `forevervm job submit`, so that `forevervm job status`
+//! can list them. The server is the source of truth for a job's state; this only remembers
+//! which jobs exist.
+
+use crate::config::ConfigManager;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use forevervm_sdk::client::job::JobHandle;
+use serde::{Deserialize, Serialize};
+use std::{fs::File, path::PathBuf};
+
+/// Only the most recent jobs are kept, so that the file doesn't grow without bound.
+const MAX_RECORDED_JOBS: usize = 100;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JobRecord {
+    #[serde(flatten)]
+    pub job: JobHandle,
+    pub code: String,
+    pub submitted_at: DateTime<Utc>,
+}
+
+pub struct JobStore {
+    path: PathBuf,
+}
+
+impl JobStore {
+    pub fn new(config_manager: &ConfigManager) -> Self {
+        Self {
+            path: config_manager.get_path().with_file_name("jobs.json"),
+        }
+    }
+
+    /// Returns the recorded jobs, oldest first.
+    pub fn load(&self) -> Result<Vec<JobRecord>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let jobs_str = std::fs::read_to_string(&self.path).context("Failed to read jobs file")?;
+        let jobs = serde_json::from_str(&jobs_str).context("Failed to parse jobs file")?;
+        Ok(jobs)
+    }
+
+    pub fn add(&self, record: JobRecord) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Hold an exclusive lock across the read-modify-write, so that concurrent
+        // submissions don't drop each other's records. It is released when `lock` drops.
+        let lock = File::create(self.path.with_extension("json.lock"))
+            .context("Failed to open jobs lock file")?;
+        lock.lock().context("Failed to lock jobs file")?;
+
+        let mut jobs = self.load()?;
+        jobs.push(record);
+        let excess = jobs.len().saturating_sub(MAX_RECORDED_JOBS);
+        jobs.drain(..excess);
+
+        let mut jobs_str =
+            serde_json::to_string_pretty(&jobs).context("Failed to serialize jobs")?;
+        jobs_str.push('\n');
+
+        // Replace the file in one step, so that a crash mid-write can't leave it truncated.
+        let temp_path = self.path.with_extension("json.tmp");
+        std::fs::write(&temp_path, jobs_str).context("Failed to write jobs file")?;
+        std::fs::rename(&temp_path, &self.path).context("Failed to write jobs file")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use forevervm_sdk::api::id_types::{InstructionSeq, MachineName};
+
+    #[test]
+    fn test_concurrent_adds_keep_every_record() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("jobs.json");
+
+        let threads: Vec<_> = (0..8)
+            .map(|thread| {
+                let store = JobStore { path: path.clone() };
+                std::thread::spawn(move || {
+                    for i in 0..5 {
+                        store
+                            .add(JobRecord {
+                                job: JobHandle {
+                                    machine: MachineName(format!("m-{thread}")),
+                                    seq: InstructionSeq(i),
+                                },
+                                code: "1 + 1".to_string(),
+                                submitted_at: Utc::now(),
+                            })
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let jobs = JobStore { path }.load().unwrap();
+        assert_eq!(jobs.len(), 40);
+    }
+}
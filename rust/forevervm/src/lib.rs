@@ -2,6 +2,8 @@
 
 pub mod commands;
 pub mod config;
+pub mod credentials;
+pub mod jobs;
 pub mod util;
 
 pub const DEFAULT_SERVER_URL: &str = "https://api.forevervm.com";
@@ -1,16 +1,33 @@
 #![deny(clippy::unwrap_used)]
 
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use forevervm::{
     commands::{
         auth::{login, logout, signup, whoami},
-        machine::{machine_list, machine_new},
+        exec::exec,
+        history::history,
+        job::{job_logs, job_status, job_submit, job_wait},
+        machine::{
+            machine_delete, machine_events, machine_extend, machine_list, machine_new,
+            machine_prune, machine_resize, machine_resume, machine_suspend, machine_tag,
+            machine_wait, PruneOptions, WaitCondition,
+        },
         repl::machine_repl,
+        tail::tail,
     },
+    util::parse_duration,
     DEFAULT_SERVER_URL,
 };
-use forevervm_sdk::api::id_types::MachineName;
+use forevervm_sdk::{
+    api::{
+        http_api::{ListMachinesRequest, MachineEventsRequest, MachineSortKey, SortOrder},
+        id_types::{InstructionSeq, MachineName},
+        tag_selector::TagSelector,
+    },
+    client::job::JobHandle,
+};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::time::Duration;
 use url::Url;
 
@@ -22,6 +39,21 @@ fn parse_key_val(s: &str) -> Result<(String, String), String> {
     Ok((s[..pos].to_string(), s[pos + 1..].to_string()))
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+enum SortKey {
+    CreatedAt,
+    ExpiresAt,
+}
+
+impl From<SortKey> for MachineSortKey {
+    fn from(key: SortKey) -> Self {
+        match key {
+            SortKey::CreatedAt => MachineSortKey::CreatedAt,
+            SortKey::ExpiresAt => MachineSortKey::ExpiresAt,
+        }
+    }
+}
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
@@ -47,6 +79,9 @@ enum Commands {
     Login {
         #[arg(long, default_value = DEFAULT_SERVER_URL)]
         api_base_url: Url,
+        /// Store the token with this credential helper program instead of the config file
+        #[arg(long)]
+        credential_helper: Option<String>,
     },
     /// Logout from your account
     Logout,
@@ -58,6 +93,77 @@ enum Commands {
     },
     /// Start a REPL session
     Repl(ReplConfig),
+    /// Run code and print its output and result.
+    ///
+    /// Exits with 0 if the code succeeded, 10 if it raised an exception, 11 if it was
+    /// interrupted, 12 if it timed out, 13 if the machine ran out of memory, or 14 if the
+    /// machine failed. Other non-zero codes mean the CLI itself failed.
+    Exec {
+        /// The code to run
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        code: Option<String>,
+        /// Read the code to run from this file
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Run on this machine instead of a new one
+        #[arg(long)]
+        machine: Option<MachineName>,
+        /// Interrupt the code if it runs for longer than this, e.g. `30s` or `10m`
+        #[arg(long, value_parser = parse_duration, default_value = "15s")]
+        timeout: chrono::Duration,
+    },
+    /// Stream the output of a machine's latest (or given) instruction until it finishes.
+    /// Exits like `forevervm exec`.
+    Tail {
+        machine_name: MachineName,
+        /// The instruction's sequence number. Defaults to the latest instruction.
+        instruction_seq: Option<i64>,
+    },
+    /// Show the instructions that have run on a machine
+    History {
+        machine_name: MachineName,
+        /// Show at most this many of the most recent instructions
+        #[arg(long, default_value = "20")]
+        limit: usize,
+        /// Also show each instruction's output
+        #[arg(long)]
+        show_output: bool,
+    },
+    /// Run long instructions in the background and check on them later
+    Job {
+        #[command(subcommand)]
+        command: JobCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum JobCommands {
+    /// Start an instruction without waiting for it, and print its job ID (`machine/seq`)
+    Submit {
+        /// The code to run
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        code: Option<String>,
+        /// Read the code to run from this file
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Run on this machine instead of a new one
+        #[arg(long)]
+        machine: Option<MachineName>,
+        /// Interrupt the instruction if it runs for longer than this, e.g. `30m` or `6h`
+        #[arg(long, value_parser = parse_duration, default_value = "6h")]
+        timeout: chrono::Duration,
+    },
+    /// Show the status of a job, or of all jobs submitted from this computer
+    Status { job: Option<JobHandle> },
+    /// Stream a job's output until it finishes. Exits like `forevervm exec`.
+    Logs { job: JobHandle },
+    /// Wait for a job to finish and print its result. Exits like `forevervm exec`.
+    Wait {
+        job: JobHandle,
+        /// Give up after this long, e.g. `30m` or `12h`
+        #[arg(long, value_parser = parse_duration, default_value = "24h")]
+        timeout: chrono::Duration,
+    },
 }
 
 #[derive(Subcommand)]
@@ -67,12 +173,111 @@ enum MachineCommands {
         /// Add tags to the machine in the format key=value
         #[arg(long = "tag", value_parser = parse_key_val, action = clap::ArgAction::Append)]
         tags: Option<Vec<(String, String)>>,
+        /// Delete the machine after this long, e.g. `2h` or `7d`
+        #[arg(long, value_parser = parse_duration)]
+        ttl: Option<chrono::Duration>,
+        /// Memory size in MB. If not specified, a default value will be used.
+        #[arg(long)]
+        memory_mb: Option<u32>,
+    },
+    /// Park a machine until it is next used, releasing its resources
+    Suspend { machine_name: MachineName },
+    /// Start a suspended machine and wait until it is running
+    Resume {
+        machine_name: MachineName,
+        /// How long to wait for the machine to start, e.g. `30s` or `2m`
+        #[arg(long, value_parser = parse_duration, default_value = "60s")]
+        timeout: chrono::Duration,
+    },
+    /// Wait until a machine is idle or running
+    Wait {
+        machine_name: MachineName,
+        /// The state to wait for
+        #[arg(long, value_enum, default_value = "idle")]
+        until: WaitCondition,
+        /// Give up after this long, e.g. `30s` or `10m`
+        #[arg(long, value_parser = parse_duration, default_value = "10m")]
+        timeout: chrono::Duration,
+    },
+    /// Change a machine's memory size
+    Resize {
+        machine_name: MachineName,
+        /// New memory size in MB
+        #[arg(long)]
+        memory_mb: u32,
+    },
+    /// Push back a machine's expiry time
+    Extend {
+        machine_name: MachineName,
+        /// How long to extend the expiry by, e.g. `2h` or `1d`
+        #[arg(long, value_parser = parse_duration)]
+        by: chrono::Duration,
     },
     /// List all machines
     List {
         /// Filter machines by tags in the format key=value
         #[arg(long = "tag", value_parser = parse_key_val, action = clap::ArgAction::Append)]
         tags: Option<Vec<(String, String)>>,
+        /// Filter machines by tag selector, e.g. `env!=prod`, `team in (a,b)`, `owner exists`
+        #[arg(short = 'l', long = "selector", action = clap::ArgAction::Append)]
+        selectors: Vec<TagSelector>,
+        /// Only show machines that are (or are not) running
+        #[arg(long)]
+        running: Option<bool>,
+        /// Only show machines that have (or do not have) a pending instruction
+        #[arg(long)]
+        pending: Option<bool>,
+        /// Sort machines by this field
+        #[arg(long, value_enum)]
+        sort: Option<SortKey>,
+        /// Sort in descending order
+        #[arg(long, requires = "sort")]
+        desc: bool,
+        /// Show at most this many machines
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Print machine lifecycle events as they happen
+    Events {
+        /// Only show events for machines with this tag, in the format key=value
+        #[arg(long = "tag", value_parser = parse_key_val, action = clap::ArgAction::Append)]
+        tags: Option<Vec<(String, String)>>,
+        /// Only show events for machines matching this tag selector
+        #[arg(short = 'l', long = "selector", action = clap::ArgAction::Append)]
+        selectors: Vec<TagSelector>,
+    },
+    /// Set or remove tags on a machine
+    Tag {
+        machine_name: MachineName,
+        /// Set a tag in the format key=value
+        #[arg(long, value_parser = parse_key_val, action = clap::ArgAction::Append)]
+        set: Vec<(String, String)>,
+        /// Remove the tag with this key
+        #[arg(long, action = clap::ArgAction::Append)]
+        unset: Vec<String>,
+    },
+    /// Delete one or more machines
+    Delete {
+        #[arg(required = true)]
+        machine_names: Vec<MachineName>,
+    },
+    /// Delete all machines matching the given filters
+    Prune {
+        /// Only prune machines with this tag, in the format key=value
+        #[arg(long = "tag", value_parser = parse_key_val, action = clap::ArgAction::Append)]
+        tags: Option<Vec<(String, String)>>,
+        /// Only prune machines created at least this long ago, e.g. `12h` or `7d`
+        #[arg(long, value_parser = parse_duration)]
+        older_than: Option<chrono::Duration>,
+        /// Only prune machines that are not running and have no pending instruction
+        #[arg(long)]
+        idle: bool,
+        /// List the machines that would be deleted without deleting them
+        #[arg(long)]
+        dry_run: bool,
+        /// Do not ask for confirmation
+        #[arg(long, short = 'y')]
+        yes: bool,
     },
     /// Start a REPL session for a specific machine
     Repl(ReplConfig),
@@ -85,8 +290,11 @@ async fn main_inner() -> anyhow::Result<()> {
         Commands::Signup { api_base_url } => {
             signup(api_base_url).await?;
         }
-        Commands::Login { api_base_url } => {
-            login(api_base_url).await?;
+        Commands::Login {
+            api_base_url,
+            credential_helper,
+        } => {
+            login(api_base_url, credential_helper).await?;
         }
         Commands::Logout => {
             logout().await?;
@@ -95,17 +303,99 @@ async fn main_inner() -> anyhow::Result<()> {
             whoami().await?;
         }
         Commands::Machine { command } => match command {
-            MachineCommands::New { tags } => {
+            MachineCommands::New {
+                tags,
+                ttl,
+                memory_mb,
+            } => {
                 let tags_map = tags
                     .map(|tags| tags.into_iter().collect::<HashMap<String, String>>())
                     .unwrap_or_default();
-                machine_new(tags_map).await?;
+                machine_new(tags_map, ttl, memory_mb).await?;
+            }
+            MachineCommands::Suspend { machine_name } => {
+                machine_suspend(machine_name).await?;
+            }
+            MachineCommands::Resume {
+                machine_name,
+                timeout,
+            } => {
+                machine_resume(machine_name, timeout).await?;
+            }
+            MachineCommands::Wait {
+                machine_name,
+                until,
+                timeout,
+            } => {
+                machine_wait(machine_name, until, timeout).await?;
             }
-            MachineCommands::List { tags } => {
+            MachineCommands::Resize {
+                machine_name,
+                memory_mb,
+            } => {
+                machine_resize(machine_name, memory_mb).await?;
+            }
+            MachineCommands::Extend { machine_name, by } => {
+                machine_extend(machine_name, by).await?;
+            }
+            MachineCommands::List {
+                tags,
+                selectors,
+                running,
+                pending,
+                sort,
+                desc,
+                limit,
+            } => {
                 let tags_map = tags
                     .map(|tags| tags.into_iter().collect::<HashMap<String, String>>())
                     .unwrap_or_default();
-                machine_list(tags_map).await?;
+                let request = ListMachinesRequest {
+                    tags: tags_map,
+                    tag_selectors: selectors,
+                    running,
+                    has_pending_instruction: pending,
+                    sort_by: sort.map(Into::into),
+                    sort_order: desc.then_some(SortOrder::Desc),
+                    ..Default::default()
+                };
+                machine_list(request, limit).await?;
+            }
+            MachineCommands::Events { tags, selectors } => {
+                let filter = MachineEventsRequest {
+                    tags: tags.unwrap_or_default().into_iter().collect(),
+                    tag_selectors: selectors,
+                };
+                machine_events(filter).await?;
+            }
+            MachineCommands::Tag {
+                machine_name,
+                set,
+                unset,
+            } => {
+                if set.is_empty() && unset.is_empty() {
+                    anyhow::bail!("Nothing to do; pass --set or --unset");
+                }
+                machine_tag(machine_name, set.into_iter().collect(), unset).await?;
+            }
+            MachineCommands::Delete { machine_names } => {
+                machine_delete(machine_names).await?;
+            }
+            MachineCommands::Prune {
+                tags,
+                older_than,
+                idle,
+                dry_run,
+                yes,
+            } => {
+                let options = PruneOptions {
+                    tags: tags.unwrap_or_default().into_iter().collect(),
+                    older_than,
+                    idle,
+                    dry_run,
+                    yes,
+                };
+                machine_prune(options).await?;
             }
             MachineCommands::Repl(config) => {
                 run_repl(config).await?;
@@ -114,11 +404,72 @@ async fn main_inner() -> anyhow::Result<()> {
         Commands::Repl(config) => {
             run_repl(config).await?;
         }
+        Commands::Exec {
+            code,
+            file,
+            machine,
+            timeout,
+        } => {
+            let code = exec(read_code(code, file)?, machine, timeout).await?;
+            if code != 0 {
+                std::process::exit(code);
+            }
+        }
+        Commands::Tail {
+            machine_name,
+            instruction_seq,
+        } => {
+            let code = tail(machine_name, instruction_seq.map(InstructionSeq)).await?;
+            if code != 0 {
+                std::process::exit(code);
+            }
+        }
+        Commands::History {
+            machine_name,
+            limit,
+            show_output,
+        } => {
+            history(machine_name, limit, show_output).await?;
+        }
+        Commands::Job { command } => match command {
+            JobCommands::Submit {
+                code,
+                file,
+                machine,
+                timeout,
+            } => {
+                job_submit(read_code(code, file)?, machine, timeout).await?;
+            }
+            JobCommands::Status { job } => {
+                job_status(job).await?;
+            }
+            JobCommands::Logs { job } => {
+                let code = job_logs(job).await?;
+                if code != 0 {
+                    std::process::exit(code);
+                }
+            }
+            JobCommands::Wait { job, timeout } => {
+                let code = job_wait(job, timeout).await?;
+                if code != 0 {
+                    std::process::exit(code);
+                }
+            }
+        },
     }
 
     Ok(())
 }
 
+/// Returns the code given on the command line, or the contents of the given file.
+fn read_code(code: Option<String>, file: Option<PathBuf>) -> anyhow::Result<String> {
+    match (code, file) {
+        (Some(code), _) => Ok(code),
+        (None, Some(file)) => Ok(std::fs::read_to_string(file)?),
+        (None, None) => anyhow::bail!("No code given"),
+    }
+}
+
 pub async fn run_repl(config: ReplConfig) -> anyhow::Result<()> {
     let instruction_timeout = Duration::from_secs(config.instruction_timeout_seconds);
     machine_repl(config.machine_name, instruction_timeout).await?;
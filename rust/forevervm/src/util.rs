@@ -1,6 +1,38 @@
 use chrono::Duration;
+use forevervm_sdk::api::api_types::ExecStatus;
 use std::{env, fmt::Display};
 
+// Exit codes that report how an instruction ended start at 10, so that scripts can tell
+// them apart from a failure of the CLI itself (1) and from usage errors, which clap
+// reports with 2.
+
+/// Exit code when an instruction finished by raising an exception.
+pub const EXIT_INSTRUCTION_ERROR: i32 = 10;
+
+/// Exit code when an instruction was interrupted before producing a result.
+pub const EXIT_INSTRUCTION_INTERRUPTED: i32 = 11;
+
+/// Exit code when an instruction ran for longer than its timeout.
+pub const EXIT_INSTRUCTION_TIMEOUT: i32 = 12;
+
+/// Exit code when the machine ran out of memory while running an instruction.
+pub const EXIT_OUT_OF_MEMORY: i32 = 13;
+
+/// Exit code when the machine failed while running an instruction.
+pub const EXIT_MACHINE_ERROR: i32 = 14;
+
+/// The exit code that reflects how an instruction ended.
+pub fn exit_code(status: ExecStatus) -> i32 {
+    match status {
+        ExecStatus::Ok => 0,
+        ExecStatus::PythonException | ExecStatus::Unknown => EXIT_INSTRUCTION_ERROR,
+        ExecStatus::Interrupted => EXIT_INSTRUCTION_INTERRUPTED,
+        ExecStatus::Timeout => EXIT_INSTRUCTION_TIMEOUT,
+        ExecStatus::OutOfMemory => EXIT_OUT_OF_MEMORY,
+        ExecStatus::MachineError => EXIT_MACHINE_ERROR,
+    }
+}
+
 pub enum ApproximateDuration {
     Days(i64),
     Hours(i64),
@@ -41,6 +73,50 @@ impl From<Duration> for ApproximateDuration {
     }
 }
 
+/// Converts a timeout given on the command line to an instruction's `timeout_seconds`.
+pub fn timeout_seconds(timeout: Duration) -> anyhow::Result<i32> {
+    timeout
+        .num_seconds()
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Timeout is too long"))
+}
+
+/// Parses a duration such as `30s`, `15m`, `12h`, `7d` or `2w`, or a combination
+/// of them like `1d12h`.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration `{s}`; expected e.g. `30m`, `12h` or `7d`");
+
+    let mut total_seconds: i64 = 0;
+    let mut digits = String::new();
+    for c in s.trim().chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+
+        let unit_seconds = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        let value: i64 = digits.parse().map_err(|_| invalid())?;
+        total_seconds = value
+            .checked_mul(unit_seconds)
+            .and_then(|seconds| total_seconds.checked_add(seconds))
+            .ok_or_else(invalid)?;
+        digits.clear();
+    }
+
+    if !digits.is_empty() || total_seconds == 0 {
+        return Err(invalid());
+    }
+
+    Duration::try_seconds(total_seconds).ok_or_else(invalid)
+}
+
 pub fn get_runner() -> String {
     let allowlist = ["npx", "uvx", "cargo"];
 
@@ -49,3 +125,39 @@ pub fn get_runner() -> String {
         _ => "cargo".to_string(),
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30s").unwrap(), Duration::seconds(30));
+        assert_eq!(parse_duration("15m").unwrap(), Duration::minutes(15));
+        assert_eq!(parse_duration(" 2w ").unwrap(), Duration::weeks(2));
+        assert_eq!(parse_duration("1d12h").unwrap(), Duration::hours(36));
+        assert_eq!(parse_duration("1h1m1s").unwrap(), Duration::seconds(3661));
+    }
+
+    #[test]
+    fn test_parse_duration_rejects_invalid_input() {
+        for input in ["", "  ", "12", "h", "1x", "1.5h", "-1h", "0s", "1h 30m"] {
+            assert!(parse_duration(input).is_err(), "accepted `{input}`");
+        }
+    }
+
+    #[test]
+    fn test_parse_duration_rejects_overflow() {
+        // Too large for an i64 at all, after multiplying by the unit, after adding, and
+        // for a chrono duration.
+        for input in [
+            "99999999999999999999s",
+            "99999999999999w",
+            "9223372036854775807s1s",
+            "9223372036854776s",
+        ] {
+            assert!(parse_duration(input).is_err(), "accepted `{input}`");
+        }
+    }
+}